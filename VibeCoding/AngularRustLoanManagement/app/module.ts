@@ -2,12 +2,14 @@
 import { NgModule } from '@angular/core';
 import { BrowserModule } from '@angular/platform-browser';
 import { ReactiveFormsModule } from '@angular/forms';
-import { HttpClientModule } from '@angular/common/http';
+import { HTTP_INTERCEPTORS, HttpClientModule } from '@angular/common/http';
 import { CommonModule } from '@angular/common';
 
 import { AppComponent } from './app.component';
 import { LoanSearchComponent } from './loan-search.component';
+import { AuthInterceptor } from './auth.interceptor';
 import { LoanService } from './loan.service';
+import { TraceContextInterceptor } from './trace-context.interceptor';
 
 @NgModule({
     declarations: [
@@ -20,7 +22,11 @@ import { LoanService } from './loan.service';
         ReactiveFormsModule,
         HttpClientModule
     ],
-    providers: [LoanService],
+    providers: [
+        LoanService,
+        { provide: HTTP_INTERCEPTORS, useClass: AuthInterceptor, multi: true },
+        { provide: HTTP_INTERCEPTORS, useClass: TraceContextInterceptor, multi: true }
+    ],
     bootstrap: [AppComponent]
 })
 export class AppModule { }
\ No newline at end of file
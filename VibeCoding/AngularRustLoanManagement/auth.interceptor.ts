@@ -0,0 +1,38 @@
+// auth.interceptor.ts
+import { Injectable } from '@angular/core';
+import {
+  HttpErrorResponse,
+  HttpEvent,
+  HttpHandler,
+  HttpInterceptor,
+  HttpRequest
+} from '@angular/common/http';
+import { Observable, throwError } from 'rxjs';
+import { catchError } from 'rxjs/operators';
+import { environment } from '../environments/environment';
+import { AuthService } from './auth.service';
+
+// Sends the signed-in user's token as `Authorization: Bearer <JWT>` on calls to the loan
+// API, which rejects them without one. A 401 means the token expired or was revoked, so it
+// is dropped and the user has to sign in again. Other hosts never see the token.
+@Injectable()
+export class AuthInterceptor implements HttpInterceptor {
+  constructor(private authService: AuthService) {}
+
+  intercept(request: HttpRequest<unknown>, next: HttpHandler): Observable<HttpEvent<unknown>> {
+    const token = this.authService.getToken();
+    if (!request.url.startsWith(environment.apiUrl) || !token) {
+      return next.handle(request);
+    }
+
+    const authorized = request.clone({ setHeaders: { Authorization: `Bearer ${token}` } });
+    return next.handle(authorized).pipe(
+      catchError((error: HttpErrorResponse) => {
+        if (error.status === 401) {
+          this.authService.clearToken();
+        }
+        return throwError(() => error);
+      })
+    );
+  }
+}
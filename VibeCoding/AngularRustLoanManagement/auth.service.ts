@@ -0,0 +1,27 @@
+// auth.service.ts
+import { Injectable } from '@angular/core';
+
+const TOKEN_KEY = 'loanApiToken';
+
+// Holds the bearer token issued at sign-in for the rest of the browser session.
+// The sign-in flow calls setToken; AuthInterceptor attaches the token to API calls.
+@Injectable({
+  providedIn: 'root'
+})
+export class AuthService {
+  getToken(): string | null {
+    return sessionStorage.getItem(TOKEN_KEY);
+  }
+
+  setToken(token: string) {
+    sessionStorage.setItem(TOKEN_KEY, token);
+  }
+
+  clearToken() {
+    sessionStorage.removeItem(TOKEN_KEY);
+  }
+
+  isSignedIn(): boolean {
+    return this.getToken() !== null;
+  }
+}
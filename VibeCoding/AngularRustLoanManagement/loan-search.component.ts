@@ -1,31 +1,43 @@
 // loan-search.component.ts
 import { Component, OnInit } from '@angular/core';
+import { HttpErrorResponse } from '@angular/common/http';
 import { FormControl, FormGroup } from '@angular/forms';
 import { debounceTime, distinctUntilChanged } from 'rxjs/operators';
-import { LoanService } from './loan.service';
+import {
+  LoanService,
+  LoanStatus,
+  LoanStatusCategory,
+  ProblemDetails,
+  ProductType,
+  SearchFacets,
+  SearchHit
+} from './loan.service';
 
+// Money and rates are exact decimal strings, e.g. "1089.45"; parse them only for display
 export interface Loan {
   loanId: string;
   customerName: string;
   propertyAddress: string;
   originationDate: string;
   maturityDate: string;
-  loanAmount: number;
-  remainingBalance: number;
-  interestRate: number;
-  monthlyPayment: number;
-  status: string;
+  loanAmount: string;
+  remainingBalance: string;
+  interestRate: string;
+  monthlyPayment: string;
+  status: LoanStatusCategory;
   productName: string;
-  productType: string;
+  productType: ProductType;
   securityName: string;
   servicerName: string;
-  currentStatus: string;
+  currentStatus: LoanStatus;
 }
 
 export interface SearchFilters {
+  q?: string;
+  similar?: string;
   customerName?: string;
-  status?: string;
-  productType?: string;
+  status?: LoanStatusCategory;
+  productType?: ProductType;
   servicerName?: string;
   minLoanAmount?: number;
   maxLoanAmount?: number;
@@ -40,6 +52,28 @@ export interface SearchFilters {
       <h2>Loan Search</h2>
       
       <form [formGroup]="searchForm" class="search-form">
+        <div class="form-row">
+          <div class="form-group">
+            <label for="q">Keywords</label>
+            <input 
+              type="text" 
+              id="q"
+              formControlName="q"
+              class="form-control"
+              placeholder="Name, address, product or security">
+          </div>
+          
+          <div class="form-group">
+            <label for="similar">Sounds Like</label>
+            <input 
+              type="text" 
+              id="similar"
+              formControlName="similar"
+              class="form-control"
+              placeholder="Approximate customer name or address">
+          </div>
+        </div>
+        
         <div class="form-row">
           <div class="form-group">
             <label for="customerName">Customer Name</label>
@@ -59,6 +93,7 @@ export interface SearchFilters {
               <option value="Paid Off">Paid Off</option>
               <option value="Delinquent">Delinquent</option>
               <option value="Default">Default</option>
+              <option value="Charged Off">Charged Off</option>
             </select>
           </div>
           
@@ -132,10 +167,24 @@ export interface SearchFilters {
         </div>
       </form>
       
+      <div *ngIf="facets" class="facets">
+        <div class="facet-group" *ngFor="let group of facetGroups">
+          <h4>{{ group.label }}</h4>
+          <button
+            type="button"
+            *ngFor="let facet of facets[group.key]"
+            (click)="applyFacet(group.control, facet.value)"
+            class="facet"
+            [class.facet-active]="searchForm.value[group.control] === facet.value">
+            {{ facet.value }} <span class="facet-count">{{ facet.count }}</span>
+          </button>
+        </div>
+      </div>
+      
       <div class="search-results">
         <div class="results-header">
           <h3>Search Results</h3>
-          <span class="results-count">{{ loans.length }} loans found</span>
+          <span class="results-count">Showing {{ loans.length }} of {{ total }} loans</span>
         </div>
         
         <div *ngIf="loading" class="loading">Loading...</div>
@@ -163,7 +212,10 @@ export interface SearchFilters {
               <tr *ngFor="let loan of loans" class="loan-row">
                 <td>{{ loan.loanId }}</td>
                 <td>{{ loan.customerName }}</td>
-                <td>{{ loan.propertyAddress }}</td>
+                <td>
+                  {{ loan.propertyAddress }}
+                  <div *ngIf="loan.headline" class="headline" [innerHTML]="loan.headline"></div>
+                </td>
                 <td>{{ loan.loanAmount | currency }}</td>
                 <td>{{ loan.remainingBalance | currency }}</td>
                 <td>{{ loan.interestRate }}%</td>
@@ -282,6 +334,40 @@ export interface SearchFilters {
       font-size: 14px;
     }
     
+    .facets {
+      display: flex;
+      gap: 30px;
+      margin-bottom: 30px;
+      flex-wrap: wrap;
+    }
+    
+    .facet-group h4 {
+      margin: 0 0 8px;
+      font-size: 14px;
+      color: #333;
+    }
+    
+    .facet {
+      display: inline-block;
+      margin: 0 6px 6px 0;
+      padding: 4px 10px;
+      border: 1px solid #ddd;
+      border-radius: 12px;
+      background: white;
+      cursor: pointer;
+      font-size: 12px;
+    }
+    
+    .facet-active {
+      border-color: #007bff;
+      background-color: #e7f1ff;
+    }
+    
+    .facet-count {
+      color: #666;
+      margin-left: 4px;
+    }
+    
     .table-container {
       overflow-x: auto;
       border-radius: 8px;
@@ -313,6 +399,12 @@ export interface SearchFilters {
       background-color: #f8f9fa;
     }
     
+    .headline {
+      color: #666;
+      font-size: 12px;
+      margin-top: 4px;
+    }
+    
     .status-badge {
       padding: 4px 8px;
       border-radius: 4px;
@@ -340,6 +432,11 @@ export interface SearchFilters {
       color: #721c24;
     }
     
+    .status-charged-off {
+      background-color: #e2e3e5;
+      color: #383d41;
+    }
+    
     .loading {
       text-align: center;
       padding: 40px;
@@ -365,12 +462,22 @@ export interface SearchFilters {
 })
 export class LoanSearchComponent implements OnInit {
   searchForm: FormGroup;
-  loans: Loan[] = [];
+  loans: SearchHit[] = [];
+  total = 0;
+  facets: SearchFacets | null = null;
   loading = false;
   error: string | null = null;
 
+  readonly facetGroups: { label: string; key: keyof SearchFacets; control: string }[] = [
+    { label: 'Status', key: 'status', control: 'status' },
+    { label: 'Product Type', key: 'productType', control: 'productType' },
+    { label: 'Servicer', key: 'servicerName', control: 'servicerName' }
+  ];
+
   constructor(private loanService: LoanService) {
     this.searchForm = new FormGroup({
+      q: new FormControl(''),
+      similar: new FormControl(''),
       customerName: new FormControl(''),
       status: new FormControl(''),
       productType: new FormControl(''),
@@ -405,6 +512,8 @@ export class LoanSearchComponent implements OnInit {
     const formValue = this.searchForm.value;
 
     // Build filters object
+    if (formValue.q) filters.q = formValue.q;
+    if (formValue.similar) filters.similar = formValue.similar;
     if (formValue.customerName) filters.customerName = formValue.customerName;
     if (formValue.status) filters.status = formValue.status;
     if (formValue.productType) filters.productType = formValue.productType;
@@ -415,13 +524,31 @@ export class LoanSearchComponent implements OnInit {
     if (formValue.originationDateTo) filters.originationDateTo = formValue.originationDateTo;
 
     this.loanService.searchLoans(filters).subscribe({
-      next: (loans) => {
-        this.loans = loans;
+      next: (response) => {
+        this.loans = response.data;
+        this.total = response.total;
+        this.facets = response.facets;
         this.loading = false;
       },
-      error: (error) => {
-        this.error = 'Failed to load loans. Please try again.';
+      error: (error: HttpErrorResponse) => {
         this.loading = false;
+        if (error.status === 401) {
+          this.loans = [];
+          this.error = 'You are not signed in or your session has expired. Please sign in again.';
+          return;
+        }
+        if (error.status === 403) {
+          this.loans = [];
+          this.error = 'Your account is not permitted to view loans.';
+          return;
+        }
+
+        const problem = error.error as ProblemDetails | null;
+        const message = problem?.errors?.map((e) => e.message).join('; ') || problem?.detail;
+        this.error = message ? `Failed to load loans: ${message}` : 'Failed to load loans. Please try again.';
+        if (problem?.correlationId) {
+          this.error += ` (reference ${problem.correlationId})`;
+        }
         console.error('Search error:', error);
       }
     });
@@ -431,6 +558,12 @@ export class LoanSearchComponent implements OnInit {
     this.searchForm.reset();
   }
 
+  // Clicking the active facet again removes that filter
+  applyFacet(control: string, value: string) {
+    const current = this.searchForm.get(control)?.value;
+    this.searchForm.get(control)?.setValue(current === value ? '' : value);
+  }
+
   getStatusClass(status: string): string {
     return `status-${status.toLowerCase().replace(/\s+/g, '-')}`;
   }
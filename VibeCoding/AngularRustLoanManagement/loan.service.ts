@@ -4,28 +4,49 @@ import { HttpClient, HttpParams } from '@angular/common/http';
 import { Observable } from 'rxjs';
 import { environment } from '../environments/environment';
 
+export type LoanStatusCategory = 'Active' | 'Delinquent' | 'Default' | 'Paid Off' | 'Charged Off';
+
+// Each currentStatus belongs to one status, e.g. '60 Days Late' to 'Delinquent'
+export type LoanStatus =
+  | 'Current'
+  | '30 Days Late'
+  | '60 Days Late'
+  | '90 Days Late'
+  | 'Default'
+  | 'In Foreclosure'
+  | 'Paid Off'
+  | 'Charged Off';
+
+export type ProductType = 'Fixed Rate' | 'Adjustable Rate' | 'Interest Only';
+
+// Money and rates are exact decimal strings, e.g. "1089.45"; parse them only for display
 export interface Loan {
   loanId: string;
   customerName: string;
   propertyAddress: string;
   originationDate: string;
   maturityDate: string;
-  loanAmount: number;
-  remainingBalance: number;
-  interestRate: number;
-  monthlyPayment: number;
-  status: string;
+  loanAmount: string;
+  remainingBalance: string;
+  interestRate: string;
+  monthlyPayment: string;
+  status: LoanStatusCategory;
   productName: string;
-  productType: string;
+  productType: ProductType;
   securityName: string;
   servicerName: string;
-  currentStatus: string;
+  currentStatus: LoanStatus;
 }
 
+// Sent as snake_case query parameters by addFilterParams; the API document at
+// /api-docs/openapi.json lists them under GET /api/v1/loans/search
 export interface SearchFilters {
+  q?: string;
+  similar?: string;
+  minSimilarity?: number;
   customerName?: string;
-  status?: string;
-  productType?: string;
+  status?: LoanStatusCategory;
+  productType?: ProductType;
   servicerName?: string;
   minLoanAmount?: number;
   maxLoanAmount?: number;
@@ -33,29 +54,190 @@ export interface SearchFilters {
   originationDateTo?: string;
 }
 
+export interface ScheduleEntry {
+  period: number;
+  paymentDate: string;
+  payment: string;
+  interest: string;
+  principal: string;
+  endingBalance: string;
+}
+
+export interface AmortizationSchedule {
+  loanId: string;
+  numberOfPayments: number;
+  computedMonthlyPayment: string;
+  storedMonthlyPayment: string;
+  paymentMismatch: boolean;
+  totalInterest: string;
+  entries: ScheduleEntry[];
+}
+
 export interface ApiResponse<T> {
   data: T;
   total: number;
   page: number;
   pageSize: number;
+  nextCursor: string | null;
+  prevCursor: string | null;
+}
+
+export interface FacetCount {
+  value: string;
+  count: number;
+}
+
+export interface SearchFacets {
+  status: FacetCount[];
+  productType: FacetCount[];
+  servicerName: FacetCount[];
+}
+
+// Search results carry a relevance rank and highlighted headline when searching with q,
+// and a similarity score when searching with similar
+export interface SearchHit extends Loan {
+  rank?: number;
+  headline?: string;
+  score?: number;
+}
+
+export interface SearchResponse extends ApiResponse<SearchHit[]> {
+  facets: SearchFacets;
+}
+
+// Filter tree for POST /api/v1/loans/query; columns are snake_case loan columns
+export type LoanFilter =
+  | { and: LoanFilter[] }
+  | { or: LoanFilter[] }
+  | { not: LoanFilter }
+  | { eq: { column: string; value: string | number } }
+  | { in: { column: string; values: (string | number)[] } }
+  | { range: { column: string; min?: string | number; max?: string | number } }
+  | { contains: { column: string; value: string } };
+
+export interface LoanQuery {
+  filter?: LoanFilter;
+  q?: string;
+  similar?: string;
+  minSimilarity?: number;
+  sort?: string;
+  page?: number;
+  pageSize?: number;
+  cursor?: string;
+}
+
+export interface ImportRowError {
+  line: number;
+  loanId: string | null;
+  errors: { field: string; message: string }[];
+}
+
+// Counts are what was written, or what would be written for a dry run
+export interface ImportReport {
+  dryRun: boolean;
+  rows: number;
+  inserted: number;
+  updated: number;
+  errors: ImportRowError[];
+}
+
+// labels, periods and every series' data line up by index, ready for a chart; data
+// values are decimal strings like the loan amounts
+export interface Trend {
+  interval: 'month' | 'quarter';
+  from: string;
+  to: string;
+  labels: string[];
+  periods: string[];
+  series: { name: string; data: string[] }[];
+}
+
+export interface TrendOptions {
+  from?: string;
+  to?: string;
+  interval?: 'month' | 'quarter';
+}
+
+// Error body of every failed request (RFC 7807 problem details)
+export interface ProblemDetails {
+  type: string;
+  title: string;
+  status: number;
+  detail?: string;
+  instance?: string;
+  correlationId?: string;
+  errors?: { field: string; message: string }[];
+  report?: ImportReport;
+  current?: Loan;
 }
 
 @Injectable({
   providedIn: 'root'
 })
 export class LoanService {
-  private apiUrl = `${environment.apiUrl}/api/loans`;
+  private apiUrl = `${environment.apiUrl}/api/v1/loans`;
 
   constructor(private http: HttpClient) {}
 
-  searchLoans(filters: SearchFilters, page: number = 1, pageSize: number = 100): Observable<Loan[]> {
+  searchLoans(
+    filters: SearchFilters,
+    page: number = 1,
+    pageSize: number = 100,
+    cursor?: string,
+    sort?: string
+  ): Observable<SearchResponse> {
     let params = new HttpParams();
     
-    // Add pagination
+    // Add pagination; a cursor from a previous response takes precedence over the page number
     params = params.set('page', page.toString());
     params = params.set('page_size', pageSize.toString());
+    if (cursor) {
+      params = params.set('cursor', cursor);
+    }
+    // Comma-separated snake_case columns, '-' prefix for descending (e.g. '-loan_amount,origination_date')
+    if (sort) {
+      params = params.set('sort', sort);
+    }
+
+    params = this.addFilterParams(params, filters);
+
+    return this.http.get<SearchResponse>(`${this.apiUrl}/search`, { params });
+  }
+
+  queryLoans(query: LoanQuery): Observable<SearchResponse> {
+    return this.http.post<SearchResponse>(`${this.apiUrl}/query`, query);
+  }
+
+  // Downloads every loan matching the filters; the server streams the file rather than paging
+  exportLoans(filters: SearchFilters, format: 'csv' | 'ndjson' = 'csv', sort?: string): Observable<Blob> {
+    let params = new HttpParams().set('format', format);
+    if (sort) {
+      params = params.set('sort', sort);
+    }
+    params = this.addFilterParams(params, filters);
+
+    return this.http.get(`${this.apiUrl}/export`, { params, responseType: 'blob' });
+  }
+
+  // Loads a CSV or NDJSON loan tape; nothing is written if any row is invalid
+  importLoans(file: File, dryRun: boolean = false): Observable<ImportReport> {
+    const body = new FormData();
+    body.append('file', file, file.name);
+    const params = new HttpParams().set('dry_run', dryRun.toString());
 
-    // Add filters
+    return this.http.post<ImportReport>(`${this.apiUrl}/import`, body, { params });
+  }
+
+  private addFilterParams(params: HttpParams, filters: SearchFilters): HttpParams {
+    if (filters.q) {
+      params = params.set('q', filters.q);
+    }
+    if (filters.similar) {
+      params = params.set('similar', filters.similar);
+    }
+    if (filters.minSimilarity !== undefined) {
+      params = params.set('min_similarity', filters.minSimilarity.toString());
+    }
     if (filters.customerName) {
       params = params.set('customer_name', filters.customerName);
     }
@@ -81,15 +263,45 @@ export class LoanService {
       params = params.set('origination_date_to', filters.originationDateTo);
     }
 
-    return this.http.get<Loan[]>(`${this.apiUrl}/search`, { params });
+    return params;
   }
 
   getLoanById(loanId: string): Observable<Loan> {
     return this.http.get<Loan>(`${this.apiUrl}/${loanId}`);
   }
 
-  getStatistics(): Observable<any> {
-    return this.http.get(`${this.apiUrl}/statistics`);
+  getSchedule(loanId: string): Observable<AmortizationSchedule> {
+    return this.http.get<AmortizationSchedule>(`${this.apiUrl}/${loanId}/schedule`);
+  }
+
+  // Statistics for the loans matching the filters; groupBy nests breakdowns outermost first,
+  // e.g. ['status', 'servicer']
+  getStatistics(filters: SearchFilters = {}, groupBy: string[] = []): Observable<any> {
+    let params = this.addFilterParams(new HttpParams(), filters);
+    if (groupBy.length > 0) {
+      params = params.set('group_by', groupBy.join(','));
+    }
+    return this.http.get(`${this.apiUrl}/statistics`, { params });
+  }
+
+  // Time series for the loans matching the filters; runoff projects forward from today,
+  // the others look back over the last two years unless from/to are given
+  getTrend(
+    metric: 'originations' | 'runoff' | 'delinquency',
+    options: TrendOptions = {},
+    filters: SearchFilters = {}
+  ): Observable<Trend> {
+    let params = this.addFilterParams(new HttpParams(), filters);
+    if (options.from) {
+      params = params.set('from', options.from);
+    }
+    if (options.to) {
+      params = params.set('to', options.to);
+    }
+    if (options.interval) {
+      params = params.set('interval', options.interval);
+    }
+    return this.http.get<Trend>(`${this.apiUrl}/trends/${metric}`, { params });
   }
 }
 
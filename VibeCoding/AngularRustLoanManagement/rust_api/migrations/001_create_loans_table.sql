@@ -0,0 +1,47 @@
+-- migrations/001_create_loans_table.sql
+-- The loans table, its indexes and the updated_at trigger from database_schema.sql, without
+-- the sample rows. Databases first built from that script also run this migration, so every
+-- statement is safe to repeat.
+CREATE TABLE IF NOT EXISTS loans (
+    loan_id VARCHAR(50) PRIMARY KEY,
+    customer_name VARCHAR(255) NOT NULL,
+    property_address TEXT NOT NULL,
+    origination_date DATE NOT NULL,
+    maturity_date DATE NOT NULL,
+    loan_amount DECIMAL(15,2) NOT NULL,
+    remaining_balance DECIMAL(15,2) NOT NULL,
+    interest_rate DECIMAL(5,3) NOT NULL,
+    monthly_payment DECIMAL(10,2) NOT NULL,
+    status VARCHAR(50) NOT NULL,
+    product_name VARCHAR(255) NOT NULL,
+    product_type VARCHAR(100) NOT NULL,
+    security_name VARCHAR(255) NOT NULL,
+    servicer_name VARCHAR(255) NOT NULL,
+    current_status VARCHAR(50) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_loans_customer_name ON loans(customer_name);
+CREATE INDEX IF NOT EXISTS idx_loans_status ON loans(status);
+CREATE INDEX IF NOT EXISTS idx_loans_product_type ON loans(product_type);
+CREATE INDEX IF NOT EXISTS idx_loans_servicer_name ON loans(servicer_name);
+CREATE INDEX IF NOT EXISTS idx_loans_loan_amount ON loans(loan_amount);
+CREATE INDEX IF NOT EXISTS idx_loans_origination_date ON loans(origination_date);
+CREATE INDEX IF NOT EXISTS idx_loans_current_status ON loans(current_status);
+
+CREATE INDEX IF NOT EXISTS idx_loans_status_product_type ON loans(status, product_type);
+CREATE INDEX IF NOT EXISTS idx_loans_servicer_status ON loans(servicer_name, status);
+
+-- Optimistic concurrency compares updated_at, so every update must move it.
+CREATE OR REPLACE FUNCTION update_updated_at_column()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at = CURRENT_TIMESTAMP;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+DROP TRIGGER IF EXISTS update_loans_updated_at ON loans;
+CREATE TRIGGER update_loans_updated_at BEFORE UPDATE ON loans
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
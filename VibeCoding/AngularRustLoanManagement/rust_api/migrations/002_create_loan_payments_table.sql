@@ -0,0 +1,16 @@
+-- migrations/002_create_loan_payments_table.sql
+-- Loans with posted payments cannot be deleted, so the payment ledger is never lost.
+CREATE TABLE IF NOT EXISTS loan_payments (
+    payment_id UUID PRIMARY KEY,
+    loan_id VARCHAR(50) NOT NULL REFERENCES loans(loan_id) ON DELETE RESTRICT,
+    payment_date DATE NOT NULL,
+    amount DECIMAL(15,2) NOT NULL,
+    interest_amount DECIMAL(15,2) NOT NULL,
+    principal_amount DECIMAL(15,2) NOT NULL,
+    escrow_amount DECIMAL(15,2) NOT NULL,
+    fees_amount DECIMAL(15,2) NOT NULL,
+    balance_after DECIMAL(15,2) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_loan_payments_loan_id_payment_date ON loan_payments(loan_id, payment_date);
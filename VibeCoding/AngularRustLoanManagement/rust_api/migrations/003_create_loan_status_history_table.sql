@@ -0,0 +1,15 @@
+-- migrations/003_create_loan_status_history_table.sql
+-- loan_id is not a foreign key so the history survives deletes.
+CREATE TABLE IF NOT EXISTS loan_status_history (
+    history_id BIGSERIAL PRIMARY KEY,
+    loan_id VARCHAR(50) NOT NULL,
+    from_status VARCHAR(50),
+    from_current_status VARCHAR(50),
+    to_status VARCHAR(50) NOT NULL,
+    to_current_status VARCHAR(50) NOT NULL,
+    reason TEXT,
+    changed_by VARCHAR(255),
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_loan_status_history_loan_id_changed_at ON loan_status_history(loan_id, changed_at);
@@ -0,0 +1,41 @@
+-- migrations/004_create_loan_audit_log.sql
+-- One row per changed field. loan_id is not a foreign key so the trail survives deletes.
+CREATE TABLE IF NOT EXISTS loan_audit_log (
+    audit_id BIGSERIAL PRIMARY KEY,
+    loan_id VARCHAR(50) NOT NULL,
+    action VARCHAR(10) NOT NULL,
+    field_name VARCHAR(64) NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    changed_by VARCHAR(255) NOT NULL,
+    transaction_id BIGINT NOT NULL DEFAULT txid_current(),
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_loan_audit_log_loan_id_changed_at ON loan_audit_log(loan_id, changed_at);
+CREATE INDEX IF NOT EXISTS idx_loan_audit_log_changed_by ON loan_audit_log(changed_by);
+
+-- Diffs OLD and NEW as JSON so new columns are audited without changing the trigger.
+-- The API sets loan_api.user_id per transaction; direct database edits fall back to the session role.
+CREATE OR REPLACE FUNCTION audit_loan_changes()
+RETURNS TRIGGER AS $$
+DECLARE
+    old_row JSONB := CASE WHEN TG_OP = 'INSERT' THEN '{}'::jsonb ELSE to_jsonb(OLD) END;
+    new_row JSONB := CASE WHEN TG_OP = 'DELETE' THEN '{}'::jsonb ELSE to_jsonb(NEW) END;
+    actor TEXT := COALESCE(NULLIF(current_setting('loan_api.user_id', true), ''), session_user);
+    changed_loan_id TEXT := COALESCE(new_row ->> 'loan_id', old_row ->> 'loan_id');
+    column_name TEXT;
+BEGIN
+    FOR column_name IN SELECT jsonb_object_keys(old_row || new_row) LOOP
+        CONTINUE WHEN column_name IN ('created_at', 'updated_at');
+        IF (old_row -> column_name) IS DISTINCT FROM (new_row -> column_name) THEN
+            INSERT INTO loan_audit_log (loan_id, action, field_name, old_value, new_value, changed_by)
+            VALUES (changed_loan_id, TG_OP, column_name, old_row ->> column_name, new_row ->> column_name, actor);
+        END IF;
+    END LOOP;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER audit_loans_changes AFTER INSERT OR UPDATE OR DELETE ON loans
+    FOR EACH ROW EXECUTE FUNCTION audit_loan_changes();
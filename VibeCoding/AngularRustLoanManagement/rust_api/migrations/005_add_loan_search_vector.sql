@@ -0,0 +1,32 @@
+-- migrations/005_add_loan_search_vector.sql
+-- Full-text search over the descriptive loan fields. The 'simple' configuration is used
+-- because names and addresses should not be stemmed or have stop words removed.
+ALTER TABLE loans ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', customer_name), 'A') ||
+    setweight(to_tsvector('simple', property_address), 'B') ||
+    setweight(to_tsvector('simple', product_name), 'C') ||
+    setweight(to_tsvector('simple', security_name), 'C')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_loans_search_vector ON loans USING GIN (search_vector);
+
+-- The search vector is derived from audited columns, so keep it out of the audit trail.
+CREATE OR REPLACE FUNCTION audit_loan_changes()
+RETURNS TRIGGER AS $$
+DECLARE
+    old_row JSONB := CASE WHEN TG_OP = 'INSERT' THEN '{}'::jsonb ELSE to_jsonb(OLD) END;
+    new_row JSONB := CASE WHEN TG_OP = 'DELETE' THEN '{}'::jsonb ELSE to_jsonb(NEW) END;
+    actor TEXT := COALESCE(NULLIF(current_setting('loan_api.user_id', true), ''), session_user);
+    changed_loan_id TEXT := COALESCE(new_row ->> 'loan_id', old_row ->> 'loan_id');
+    column_name TEXT;
+BEGIN
+    FOR column_name IN SELECT jsonb_object_keys(old_row || new_row) LOOP
+        CONTINUE WHEN column_name IN ('created_at', 'updated_at', 'search_vector');
+        IF (old_row -> column_name) IS DISTINCT FROM (new_row -> column_name) THEN
+            INSERT INTO loan_audit_log (loan_id, action, field_name, old_value, new_value, changed_by)
+            VALUES (changed_loan_id, TG_OP, column_name, old_row ->> column_name, new_row ->> column_name, actor);
+        END IF;
+    END LOOP;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
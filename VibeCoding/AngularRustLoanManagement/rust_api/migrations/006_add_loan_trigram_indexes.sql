@@ -0,0 +1,6 @@
+-- migrations/006_add_loan_trigram_indexes.sql
+-- Trigram indexes behind the typo-tolerant similarity search on names and addresses.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_loans_customer_name_trgm ON loans USING GIN (customer_name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_loans_property_address_trgm ON loans USING GIN (property_address gin_trgm_ops);
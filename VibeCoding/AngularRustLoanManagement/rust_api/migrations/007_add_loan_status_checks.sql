@@ -0,0 +1,23 @@
+-- migrations/007_add_loan_status_checks.sql
+-- Restricts status, current_status and product_type to the values of the StatusCategory,
+-- LoanStatus and ProductType enums in models.rs. Each current_status belongs to exactly one
+-- status, so the pair is checked together. Rows holding any other value make this migration
+-- fail; correct them first.
+ALTER TABLE loans DROP CONSTRAINT IF EXISTS loans_status_check;
+ALTER TABLE loans ADD CONSTRAINT loans_status_check CHECK (
+    (status, current_status) IN (
+        ('Active', 'Current'),
+        ('Delinquent', '30 Days Late'),
+        ('Delinquent', '60 Days Late'),
+        ('Delinquent', '90 Days Late'),
+        ('Default', 'Default'),
+        ('Default', 'In Foreclosure'),
+        ('Paid Off', 'Paid Off'),
+        ('Charged Off', 'Charged Off')
+    )
+);
+
+ALTER TABLE loans DROP CONSTRAINT IF EXISTS loans_product_type_check;
+ALTER TABLE loans ADD CONSTRAINT loans_product_type_check CHECK (
+    product_type IN ('Fixed Rate', 'Adjustable Rate', 'Interest Only')
+);
@@ -0,0 +1,250 @@
+// src/auth.rs
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{http::header, web, Error, HttpMessage};
+use anyhow::{bail, Context, Result};
+use futures_util::future::LocalBoxFuture;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use crate::errors::ApiError;
+
+/// Access levels, ordered so that each role includes the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Servicer,
+    Admin,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+    /// Set for external servicer partners; limits them to loans with this `servicer_name`.
+    servicer_name: Option<String>,
+}
+
+/// The authenticated caller, inserted into the request extensions by [`JwtAuth`].
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub user_id: String,
+    pub role: Role,
+    pub scope: ServicerScope,
+}
+
+/// The loans a caller may see. Only built from a verified token, so services that take
+/// a scope cannot be handed an unrestricted one by mistake.
+#[derive(Debug, Clone)]
+pub struct ServicerScope {
+    servicer_name: Option<String>,
+}
+
+impl ServicerScope {
+    /// The servicer the caller is limited to, or `None` for internal users who see every loan.
+    pub fn servicer_name(&self) -> Option<&str> {
+        self.servicer_name.as_deref()
+    }
+
+    pub fn permits(&self, servicer_name: &str) -> bool {
+        match self.servicer_name() {
+            Some(scope) => scope == servicer_name,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ServicerScope {
+    pub fn for_tests(servicer_name: Option<&str>) -> ServicerScope {
+        ServicerScope { servicer_name: servicer_name.map(str::to_string) }
+    }
+}
+
+pub struct AuthConfig {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl AuthConfig {
+    /// Reads the token verification settings:
+    /// `JWT_ALGORITHM` (HS256 or RS256), `JWT_SECRET` for HS256, `JWT_PUBLIC_KEY_PATH` (PEM)
+    /// for RS256, and the optional `JWT_ISSUER` and `JWT_AUDIENCE`.
+    pub fn from_env() -> Result<Self> {
+        let algorithm = env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+
+        let (key, algorithm) = match algorithm.as_str() {
+            "HS256" => {
+                let secret = env::var("JWT_SECRET").context("JWT_SECRET must be set for HS256")?;
+                (DecodingKey::from_secret(secret.as_bytes()), Algorithm::HS256)
+            }
+            "RS256" => {
+                let path = env::var("JWT_PUBLIC_KEY_PATH")
+                    .context("JWT_PUBLIC_KEY_PATH must be set for RS256")?;
+                let pem = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read JWT public key {}", path))?;
+                (DecodingKey::from_rsa_pem(&pem)?, Algorithm::RS256)
+            }
+            other => bail!("Unsupported JWT_ALGORITHM {}", other),
+        };
+
+        let mut validation = Validation::new(algorithm);
+        if let Ok(issuer) = env::var("JWT_ISSUER") {
+            validation.set_issuer(&[issuer]);
+        }
+        match env::var("JWT_AUDIENCE") {
+            Ok(audience) => validation.set_audience(&[audience]),
+            Err(_) => validation.validate_aud = false,
+        }
+
+        Ok(AuthConfig { key, validation })
+    }
+
+    fn authenticate(&self, req: &ServiceRequest) -> Result<Principal, &'static str> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or("Missing bearer token")?;
+
+        let claims = decode::<Claims>(token, &self.key, &self.validation)
+            .map_err(|_| "Invalid or expired token")?
+            .claims;
+
+        Ok(Principal {
+            user_id: claims.sub,
+            role: claims.role,
+            scope: ServicerScope {
+                servicer_name: claims.servicer_name,
+            },
+        })
+    }
+}
+
+/// Rejects requests without a valid bearer token and attaches the caller's [`Principal`].
+pub struct JwtAuth;
+
+impl<S, B> Transform<S, ServiceRequest> for JwtAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = JwtAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(JwtAuthMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct JwtAuthMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for JwtAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let authenticated = match req.app_data::<web::Data<AuthConfig>>() {
+                Some(config) => config.authenticate(&req),
+                None => Err("Authentication is not configured"),
+            };
+
+            match authenticated {
+                Ok(principal) => {
+                    req.extensions_mut().insert(principal);
+                    let res = service.call(req).await?;
+                    Ok(res.map_into_left_body())
+                }
+                Err(message) => {
+                    let res = req.error_response(ApiError::Unauthorized(message));
+                    Ok(res.map_into_right_body())
+                }
+            }
+        })
+    }
+}
+
+/// Route middleware that requires the authenticated caller to hold at least the given role.
+pub struct RequireRole(pub Role);
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireRoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleMiddleware {
+            service: Rc::new(service),
+            role: self.0,
+        }))
+    }
+}
+
+pub struct RequireRoleMiddleware<S> {
+    service: Rc<S>,
+    role: Role,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let required = self.role;
+
+        Box::pin(async move {
+            let role = req.extensions().get::<Principal>().map(|principal| principal.role);
+
+            match role {
+                Some(role) if role >= required => {
+                    let res = service.call(req).await?;
+                    Ok(res.map_into_left_body())
+                }
+                Some(_) => {
+                    let error = ApiError::Forbidden("Insufficient role for this operation");
+                    Ok(req.error_response(error).map_into_right_body())
+                }
+                None => {
+                    let error = ApiError::Unauthorized("Missing bearer token");
+                    Ok(req.error_response(error).map_into_right_body())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,331 @@
+// src/errors.rs
+//! Error responses. Every failure is rendered as an RFC 7807 `application/problem+json`
+//! body carrying the request's correlation ID, which is also returned in the
+//! `X-Correlation-ID` header of every response and written to the logs.
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{error, Error, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use futures_util::future::LocalBoxFuture;
+use serde::Serialize;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use tracing_actix_web::RootSpan;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::{FieldError, ImportReport, Loan};
+
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+#[derive(Clone)]
+struct RequestContext {
+    correlation_id: String,
+    path: String,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// Request fields that failed validation.
+    Validation(Vec<FieldError>),
+    /// A loan tape with invalid rows; nothing was imported.
+    ImportRejected(ImportReport),
+    BadRequest(String),
+    Unauthorized(&'static str),
+    Forbidden(&'static str),
+    NotFound(String),
+    /// The loan already exists, was modified since the client read it, or has records that
+    /// depend on it; carries the stored row when there is one.
+    Conflict { message: &'static str, current: Option<Box<Loan>> },
+    PayloadTooLarge(String),
+    /// The database could not be reached or has no free connections.
+    Unavailable(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+/// Problem details as defined by RFC 7807, plus the members this API adds.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem<'a> {
+    /// Identifies the kind of problem, e.g. `/problems/validation`.
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<String>,
+    /// Also returned in the `X-Correlation-ID` header and written to the server logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+    /// The fields that failed validation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<&'a [FieldError]>,
+    /// The rows of a rejected loan tape import.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<&'a ImportReport>,
+    /// The stored loan, when a write conflicts with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<&'a Loan>,
+}
+
+impl ApiError {
+    pub fn loan_not_found() -> Self {
+        ApiError::NotFound("Loan not found".to_string())
+    }
+
+    /// The `type` URI and `title` of each kind of problem. The URIs are identifiers for
+    /// clients to match on and are not meant to be dereferenced.
+    fn kind(&self) -> (&'static str, &'static str) {
+        match self {
+            ApiError::Validation(_) | ApiError::ImportRejected(_) => {
+                ("/problems/validation", "Validation failed")
+            }
+            ApiError::BadRequest(_) => ("/problems/bad-request", "Bad request"),
+            ApiError::Unauthorized(_) => ("/problems/unauthorized", "Authentication required"),
+            ApiError::Forbidden(_) => ("/problems/forbidden", "Forbidden"),
+            ApiError::NotFound(_) => ("/problems/not-found", "Not found"),
+            ApiError::Conflict { .. } => ("/problems/conflict", "Conflict"),
+            ApiError::PayloadTooLarge(_) => ("/problems/payload-too-large", "Payload too large"),
+            ApiError::Unavailable(_) => ("/problems/unavailable", "Service unavailable"),
+            ApiError::Internal(_) => ("/problems/internal", "Internal server error"),
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            ApiError::Validation(_) => None,
+            ApiError::ImportRejected(_) => Some("No loans were imported".to_string()),
+            ApiError::BadRequest(message)
+            | ApiError::NotFound(message)
+            | ApiError::PayloadTooLarge(message) => Some(message.clone()),
+            ApiError::Unauthorized(message) | ApiError::Forbidden(message) => {
+                Some(message.to_string())
+            }
+            ApiError::Conflict { message, .. } => Some(message.to_string()),
+            // Only the outermost context is shown; the causes may describe the database.
+            ApiError::Unavailable(error) | ApiError::Internal(error) => Some(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unavailable(error) | ApiError::Internal(error) => write!(f, "{:#}", error),
+            _ => match self.detail() {
+                Some(detail) => write!(f, "{}: {}", self.kind().1, detail),
+                None => f.write_str(self.kind().1),
+            },
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Validation(_) | ApiError::ImportRejected(_) | ApiError::BadRequest(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let context = REQUEST.try_with(RequestContext::clone).ok();
+        let correlation_id = context.as_ref().map(|context| context.correlation_id.as_str());
+        if status.is_server_error() {
+            tracing::error!(correlation_id, "{}", self);
+        }
+
+        let (problem_type, title) = self.kind();
+        let problem = Problem {
+            problem_type,
+            title,
+            status: status.as_u16(),
+            detail: self.detail(),
+            instance: context.as_ref().map(|context| context.path.clone()),
+            correlation_id: correlation_id.map(str::to_string),
+            errors: match self {
+                ApiError::Validation(errors) => Some(errors),
+                _ => None,
+            },
+            report: match self {
+                ApiError::ImportRejected(report) => Some(report),
+                _ => None,
+            },
+            current: match self {
+                ApiError::Conflict { current, .. } => current.as_deref(),
+                _ => None,
+            },
+        };
+
+        let mut response = HttpResponse::build(status);
+        response.content_type("application/problem+json");
+        if let ApiError::Unauthorized(_) = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.json(problem)
+    }
+}
+
+/// Service errors surface as `anyhow::Error`; lost connections and an exhausted pool are
+/// reported as the database being unavailable, anything else as an internal error.
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let unavailable = match error.downcast_ref::<sqlx::Error>() {
+            Some(
+                sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_),
+            ) => true,
+            Some(sqlx::Error::Database(db_error)) => {
+                db_error.code().is_some_and(|code| is_unavailable_code(&code))
+            }
+            _ => false,
+        };
+
+        if unavailable {
+            ApiError::Unavailable(error)
+        } else {
+            ApiError::Internal(error)
+        }
+    }
+}
+
+/// SQLSTATEs for a server that refuses or drops connections: class 08 (connection
+/// exceptions), class 28 (rejected credentials), 3D000 (no such database), 53300 (too many
+/// connections), 55000 (database not accepting connections) and 57P0x (shutting down or
+/// starting up).
+fn is_unavailable_code(code: &str) -> bool {
+    code.starts_with("08")
+        || code.starts_with("28")
+        || code.starts_with("57P0")
+        || matches!(code, "3D000" | "53300" | "55000")
+}
+
+impl From<actix_multipart::MultipartError> for ApiError {
+    fn from(error: actix_multipart::MultipartError) -> Self {
+        ApiError::BadRequest(error.to_string())
+    }
+}
+
+/// Reports query strings that do not deserialize, such as an unknown status, as validation
+/// failures. Serde's message lists the allowed values.
+pub fn query_error(err: error::QueryPayloadError, _req: &HttpRequest) -> Error {
+    let message = match &err {
+        error::QueryPayloadError::Deserialize(inner) => inner.to_string(),
+        other => other.to_string(),
+    };
+    ApiError::Validation(vec![FieldError { field: "query", message }]).into()
+}
+
+pub fn json_error(err: error::JsonPayloadError, _req: &HttpRequest) -> Error {
+    match err {
+        error::JsonPayloadError::Deserialize(inner) => {
+            let message = inner.to_string();
+            ApiError::Validation(vec![FieldError { field: "body", message }]).into()
+        }
+        error::JsonPayloadError::Overflow { .. }
+        | error::JsonPayloadError::OverflowKnownLength { .. } => {
+            ApiError::PayloadTooLarge(err.to_string()).into()
+        }
+        other => ApiError::BadRequest(other.to_string()).into(),
+    }
+}
+
+/// Path segments that do not deserialize name something that does not exist.
+pub fn path_error(err: error::PathError, _req: &HttpRequest) -> Error {
+    let error::PathError::Deserialize(inner) = err else {
+        return ApiError::NotFound(err.to_string()).into();
+    };
+    ApiError::NotFound(inner.to_string()).into()
+}
+
+pub async fn route_not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound("No such endpoint".to_string()))
+}
+
+/// Gives every request a correlation ID, taken from an incoming `X-Correlation-ID` header
+/// when it is a reasonable token and generated otherwise, and echoes it on the response.
+pub struct CorrelationId;
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = CorrelationIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorrelationIdMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CorrelationIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let correlation_id = req
+            .headers()
+            .get(&CORRELATION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_token(value))
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        if let Some(span) = req.extensions().get::<RootSpan>() {
+            span.record("correlation_id", correlation_id.as_str());
+        }
+        let context = RequestContext {
+            correlation_id: correlation_id.clone(),
+            path: req.path().to_string(),
+        };
+
+        Box::pin(REQUEST.scope(context, async move {
+            let mut res = service.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+                res.headers_mut().insert(CORRELATION_ID_HEADER, value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"-_.:".contains(&byte))
+}
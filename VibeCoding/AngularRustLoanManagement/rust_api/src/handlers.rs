@@ -1,56 +1,657 @@
 // src/handlers.rs
-use actix_web::{web, HttpResponse, Result};
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{error, web, HttpResponse, Result};
+use anyhow::Context;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use chrono::Utc;
 use sqlx::PgPool;
-use crate::models::{SearchFilters};
-use crate::services::LoanService;
+use crate::models::{
+    AmortizationSchedule, AuditEntry, AuditFilters, CreateLoanRequest, DeleteLoanParams,
+    ExportParams, ImportParams, ImportReport, ImportRow, ImportUpload, Loan, LoanFileFormat,
+    LoanPayment, LoanQuery, LoanStatusChange, PatchLoanRequest, PostPaymentRequest,
+    SearchFilters, SearchResponse, Statistics, StatisticsParams, StatusTransitionRequest, Trend,
+    TrendMetric, TrendParams, UpdateLoanRequest,
+};
+use crate::auth::Principal;
+use crate::errors::{ApiError, Problem};
+use crate::services::{LoanService, WriteOutcome};
 
 pub mod loan_handlers {
     use super::*;
 
+    const MAX_IMPORT_BYTES: usize = 50 * 1024 * 1024;
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/search",
+        tag = "loans",
+        params(SearchFilters),
+        responses(
+            (status = 200, description = "A page of matching loans", body = SearchResponse),
+            (status = 400, description = "Invalid request", body = Problem),
+        )
+    )]
     pub async fn search_loans(
         pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        query: web::Query<SearchFilters>,
+    ) -> Result<HttpResponse, ApiError> {
+        run_search(&pool, &principal, query.into_inner().into_query()).await
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/loans/query",
+        tag = "loans",
+        request_body = LoanQuery,
+        responses(
+            (status = 200, description = "A page of matching loans", body = SearchResponse),
+            (status = 400, description = "Invalid request", body = Problem),
+        )
+    )]
+    pub async fn query_loans(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        body: web::Json<LoanQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        run_search(&pool, &principal, body.into_inner()).await
+    }
+
+    async fn run_search(
+        pool: &PgPool,
+        principal: &Principal,
+        query: LoanQuery,
+    ) -> Result<HttpResponse, ApiError> {
+        let errors = query.validate();
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        let response = LoanService::search_loans(pool, query, &principal.scope)
+            .await
+            .context("Failed to search loans")?;
+        Ok(HttpResponse::Ok().json(response))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/export",
+        tag = "loans",
+        params(SearchFilters, ExportParams),
+        responses(
+            (status = 200, description = "Every matching loan", content(
+                (String = "text/csv"),
+                (String = "application/x-ndjson")
+            )),
+            (status = 400, description = "Invalid request", body = Problem),
+        )
+    )]
+    pub async fn export_loans(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
         query: web::Query<SearchFilters>,
-    ) -> Result<HttpResponse> {
-        match LoanService::search_loans(&pool, query.into_inner()).await {
-            Ok(response) => Ok(HttpResponse::Ok().json(response.data)),
-            Err(e) => {
-                log::error!("Failed to search loans: {}", e);
-                Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to search loans"
-                })))
+        params: web::Query<ExportParams>,
+    ) -> Result<HttpResponse, ApiError> {
+        let query = query.into_inner().into_query();
+        let errors = query.validate();
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        let loans =
+            LoanService::export_loans(pool.get_ref().clone(), query, principal.scope.clone());
+        let (content_type, filename) = match params.format {
+            LoanFileFormat::Csv => ("text/csv", "loans.csv"),
+            LoanFileFormat::Ndjson => ("application/x-ndjson", "loans.ndjson"),
+        };
+
+        Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(filename.to_string())],
+            })
+            .streaming(encode_export(params.format, loans)))
+    }
+
+    /// Accepts a loan tape as the `file` field of a multipart upload.
+    #[utoipa::path(
+        post,
+        path = "/api/v1/loans/import",
+        tag = "loans",
+        params(ImportParams),
+        request_body(content = ImportUpload, content_type = "multipart/form-data"),
+        responses(
+            (status = 200, description = "What was imported, or would be", body = ImportReport),
+            (status = 400, description = "Invalid file or rows", body = Problem),
+            (status = 413, description = "File too large", body = Problem),
+        )
+    )]
+    pub async fn import_loans(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        params: web::Query<ImportParams>,
+        mut payload: Multipart,
+    ) -> Result<HttpResponse, ApiError> {
+        let mut upload = None;
+        while let Some(mut field) = payload.try_next().await? {
+            if field.name() != Some("file") {
+                continue;
             }
+
+            let format = params.format.or_else(|| {
+                LoanFileFormat::detect(
+                    field.content_type().map(|mime| mime.essence_str()),
+                    field.content_disposition().and_then(|disposition| disposition.get_filename()),
+                )
+            });
+            let mut bytes = Vec::new();
+            while let Some(chunk) = field.try_next().await? {
+                if bytes.len() + chunk.len() > MAX_IMPORT_BYTES {
+                    return Err(ApiError::PayloadTooLarge(format!(
+                        "Files are limited to {} MiB",
+                        MAX_IMPORT_BYTES >> 20
+                    )));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            upload = Some((format, bytes));
+        }
+
+        let Some((format, bytes)) = upload else {
+            return Err(ApiError::BadRequest(
+                "Expected the loan tape in a multipart field named file".to_string(),
+            ));
+        };
+        let Some(format) = format else {
+            return Err(ApiError::BadRequest(
+                "Unrecognised file type; upload a .csv or .ndjson file or set format".to_string(),
+            ));
+        };
+
+        let rows = ImportRow::parse_all(&bytes, format);
+        if rows.is_empty() {
+            return Err(ApiError::BadRequest("The file does not contain any loans".to_string()));
+        }
+
+        let report = LoanService::import_loans(&pool, rows, params.dry_run, &principal)
+            .await
+            .context("Failed to import loans")?;
+        if !report.dry_run && !report.errors.is_empty() {
+            return Err(ApiError::ImportRejected(report));
         }
+        Ok(HttpResponse::Ok().json(report))
     }
 
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/{loan_id}",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        responses(
+            (status = 200, description = "The loan", body = Loan),
+            (status = 404, description = "Loan not found", body = Problem),
+        )
+    )]
     pub async fn get_loan_by_id(
         pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
         path: web::Path<String>,
-    ) -> Result<HttpResponse> {
+    ) -> Result<HttpResponse, ApiError> {
         let loan_id = path.into_inner();
         
-        match LoanService::get_loan_by_id(&pool, &loan_id).await {
-            Ok(Some(loan)) => Ok(HttpResponse::Ok().json(loan)),
-            Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Loan not found"
-            }))),
-            Err(e) => {
-                log::error!("Failed to get loan by id: {}", e);
-                Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to retrieve loan"
-                })))
+        match LoanService::get_loan_by_id(&pool, &loan_id, &principal.scope)
+            .await
+            .context("Failed to retrieve loan")?
+        {
+            Some(loan) => Ok(HttpResponse::Ok().json(loan)),
+            None => Err(ApiError::loan_not_found()),
+        }
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/statistics",
+        tag = "loans",
+        params(SearchFilters, StatisticsParams),
+        responses(
+            (status = 200, description = "Pool metrics of the matching loans", body = Statistics),
+            (status = 400, description = "Invalid request", body = Problem),
+        )
+    )]
+    pub async fn get_statistics(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        filters: web::Query<SearchFilters>,
+        params: web::Query<StatisticsParams>,
+    ) -> Result<HttpResponse, ApiError> {
+        let query = filters.into_inner().into_query();
+        let mut errors = query.validate();
+        let group_by = params.dimensions().unwrap_or_else(|error| {
+            errors.push(error);
+            Vec::new()
+        });
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        let stats = LoanService::get_statistics(&pool, query, &group_by, &principal.scope)
+            .await
+            .context("Failed to retrieve statistics")?;
+        Ok(HttpResponse::Ok().json(stats))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/trends/{metric}",
+        tag = "loans",
+        params(("metric" = TrendMetric, Path), SearchFilters, TrendParams),
+        responses(
+            (status = 200, description = "The time series", body = Trend),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 404, description = "Unknown metric", body = Problem),
+        )
+    )]
+    pub async fn get_trend(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        metric: web::Path<TrendMetric>,
+        filters: web::Query<SearchFilters>,
+        params: web::Query<TrendParams>,
+    ) -> Result<HttpResponse, ApiError> {
+        let metric = metric.into_inner();
+        let query = filters.into_inner().into_query();
+        let mut errors = query.validate();
+        let range = match metric.range(&params, Utc::now().date_naive()) {
+            Ok(range) => range,
+            Err(error) => {
+                errors.push(error);
+                return Err(ApiError::Validation(errors));
             }
+        };
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        let trend = LoanService::get_trend(&pool, metric, query, &range, &principal.scope)
+            .await
+            .context("Failed to retrieve trend")?;
+        Ok(HttpResponse::Ok().json(trend))
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/loans",
+        tag = "loans",
+        request_body = CreateLoanRequest,
+        responses(
+            (status = 201, description = "The created loan", body = Loan),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 409, description = "Already exists; carries the stored loan", body = Problem),
+        )
+    )]
+    pub async fn create_loan(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        body: web::Json<CreateLoanRequest>,
+    ) -> Result<HttpResponse, ApiError> {
+        let outcome = LoanService::create_loan(&pool, body.into_inner(), &principal)
+            .await
+            .context("Failed to create loan")?;
+        write_response(outcome, "Loan already exists", |loan| {
+            HttpResponse::Created().json(loan)
+        })
+    }
+
+    #[utoipa::path(
+        put,
+        path = "/api/v1/loans/{loan_id}",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        request_body = UpdateLoanRequest,
+        responses(
+            (status = 200, description = "The updated loan", body = Loan),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 404, description = "Loan not found", body = Problem),
+            (status = 409, description = "Changed since it was read", body = Problem),
+        )
+    )]
+    pub async fn update_loan(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        body: web::Json<UpdateLoanRequest>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        let outcome = LoanService::update_loan(&pool, &loan_id, body.into_inner(), &principal)
+            .await
+            .context("Failed to update loan")?;
+        write_response(outcome, "Loan was modified by another user", |loan| {
+            HttpResponse::Ok().json(loan)
+        })
+    }
+
+    #[utoipa::path(
+        patch,
+        path = "/api/v1/loans/{loan_id}",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        request_body = PatchLoanRequest,
+        responses(
+            (status = 200, description = "The updated loan", body = Loan),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 404, description = "Loan not found", body = Problem),
+            (status = 409, description = "Changed since it was read", body = Problem),
+        )
+    )]
+    pub async fn patch_loan(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        body: web::Json<PatchLoanRequest>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        let outcome = LoanService::patch_loan(&pool, &loan_id, body.into_inner(), &principal)
+            .await
+            .context("Failed to update loan")?;
+        write_response(outcome, "Loan was modified by another user", |loan| {
+            HttpResponse::Ok().json(loan)
+        })
+    }
+
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/loans/{loan_id}",
+        tag = "loans",
+        params(("loan_id" = String, Path), DeleteLoanParams),
+        responses(
+            (status = 204, description = "The loan was deleted"),
+            (status = 404, description = "Loan not found", body = Problem),
+            (
+                status = 409,
+                description = "Changed since it was read, or has posted payments",
+                body = Problem
+            ),
+        )
+    )]
+    pub async fn delete_loan(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        query: web::Query<DeleteLoanParams>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        let outcome = LoanService::delete_loan(&pool, &loan_id, query.updated_at, &principal)
+            .await
+            .context("Failed to delete loan")?;
+        write_response(outcome, "Loan was modified by another user", |()| {
+            HttpResponse::NoContent().finish()
+        })
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/loans/{loan_id}/payments",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        request_body = PostPaymentRequest,
+        responses(
+            (status = 201, description = "The posted payment", body = LoanPayment),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 404, description = "Loan not found", body = Problem),
+        )
+    )]
+    pub async fn post_payment(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        body: web::Json<PostPaymentRequest>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        let outcome = LoanService::post_payment(&pool, &loan_id, body.into_inner(), &principal)
+            .await
+            .context("Failed to post payment")?;
+        write_response(outcome, "Payment conflicts with the loan", |payment| {
+            HttpResponse::Created().json(payment)
+        })
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/{loan_id}/payments",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        responses(
+            (status = 200, description = "Payments, oldest first", body = Vec<LoanPayment>),
+            (status = 404, description = "Loan not found", body = Problem),
+        )
+    )]
+    pub async fn get_payments(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        match LoanService::get_payments(&pool, &loan_id, &principal.scope)
+            .await
+            .context("Failed to retrieve payments")?
+        {
+            Some(payments) => Ok(HttpResponse::Ok().json(payments)),
+            None => Err(ApiError::loan_not_found()),
         }
     }
 
-    pub async fn get_statistics(pool: web::Data<PgPool>) -> Result<HttpResponse> {
-        match LoanService::get_statistics(&pool).await {
-            Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
-            Err(e) => {
-                log::error!("Failed to get statistics: {}", e);
-                Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Failed to retrieve statistics"
-                })))
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/{loan_id}/schedule",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        responses(
+            (status = 200, description = "Amortization schedule", body = AmortizationSchedule),
+            (status = 404, description = "Loan not found", body = Problem),
+        )
+    )]
+    pub async fn get_schedule(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        match LoanService::get_schedule(&pool, &loan_id, &principal.scope)
+            .await
+            .context("Failed to retrieve amortization schedule")?
+        {
+            Some(schedule) => Ok(HttpResponse::Ok().json(schedule)),
+            None => Err(ApiError::loan_not_found()),
+        }
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/loans/{loan_id}/status",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        request_body = StatusTransitionRequest,
+        responses(
+            (status = 200, description = "The loan in its new status", body = Loan),
+            (status = 400, description = "Invalid request", body = Problem),
+            (status = 404, description = "Loan not found", body = Problem),
+            (status = 409, description = "Changed since it was read", body = Problem),
+        )
+    )]
+    pub async fn transition_status(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        body: web::Json<StatusTransitionRequest>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+        let request = body.into_inner();
+
+        let outcome = LoanService::transition_status(&pool, &loan_id, request, &principal)
+            .await
+            .context("Failed to change loan status")?;
+        write_response(outcome, "Loan was modified by another user", |loan| {
+            HttpResponse::Ok().json(loan)
+        })
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/{loan_id}/status-history",
+        tag = "loans",
+        params(("loan_id" = String, Path)),
+        responses(
+            (status = 200, description = "Changes, oldest first", body = Vec<LoanStatusChange>),
+            (status = 404, description = "Loan not found", body = Problem),
+        )
+    )]
+    pub async fn get_status_history(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        match LoanService::get_status_history(&pool, &loan_id, &principal.scope)
+            .await
+            .context("Failed to retrieve status history")?
+        {
+            Some(history) => Ok(HttpResponse::Ok().json(history)),
+            None => Err(ApiError::loan_not_found()),
+        }
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/loans/{loan_id}/audit",
+        tag = "loans",
+        params(("loan_id" = String, Path), AuditFilters),
+        responses(
+            (status = 200, description = "Changes, oldest first", body = Vec<AuditEntry>),
+        )
+    )]
+    pub async fn get_audit_trail(
+        pool: web::Data<PgPool>,
+        principal: web::ReqData<Principal>,
+        path: web::Path<String>,
+        query: web::Query<AuditFilters>,
+    ) -> Result<HttpResponse, ApiError> {
+        let loan_id = path.into_inner();
+
+        let filters = query.into_inner();
+        let entries = LoanService::get_audit_trail(&pool, &loan_id, filters, &principal.scope)
+            .await
+            .context("Failed to retrieve audit trail")?;
+        Ok(HttpResponse::Ok().json(entries))
+    }
+
+    /// Encodes exported loans one row at a time. A failure part-way through can no longer
+    /// change the status code, so it is logged and the response is cut short.
+    fn encode_export(
+        format: LoanFileFormat,
+        loans: impl Stream<Item = anyhow::Result<Loan>> + 'static,
+    ) -> impl Stream<Item = Result<web::Bytes>> + 'static {
+        // The header is sent on its own so an export matching no loans is still a CSV file.
+        let header = match format {
+            LoanFileFormat::Csv => Some(csv_record(|writer| writer.write_record(Loan::CSV_HEADER))),
+            LoanFileFormat::Ndjson => None,
+        };
+
+        let rows = loans.map(move |loan| {
+            loan.and_then(|loan| match format {
+                LoanFileFormat::Csv => csv_record(|writer| writer.serialize(&loan)),
+                LoanFileFormat::Ndjson => {
+                    let mut line = serde_json::to_vec(&loan)?;
+                    line.push(b'\n');
+                    Ok(line)
+                }
+            })
+        });
+
+        futures_util::stream::iter(header).chain(rows).map(|encoded| {
+            encoded.map(web::Bytes::from).map_err(|e| {
+                tracing::error!("Failed to export loans: {:#}", e);
+                error::ErrorInternalServerError("Failed to export loans")
+            })
+        })
+    }
+
+    fn csv_record(
+        write: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| anyhow::anyhow!("{}", e.error()))
+    }
+
+    fn write_response<T>(
+        outcome: WriteOutcome<T>,
+        conflict_message: &'static str,
+        applied: impl FnOnce(T) -> HttpResponse,
+    ) -> Result<HttpResponse, ApiError> {
+        match outcome {
+            WriteOutcome::Applied(value) => Ok(applied(value)),
+            WriteOutcome::NotFound => Err(ApiError::loan_not_found()),
+            WriteOutcome::Conflict(current) => {
+                Err(ApiError::Conflict { message: conflict_message, current })
             }
+            WriteOutcome::Invalid(errors) => Err(ApiError::Validation(errors)),
+            WriteOutcome::Blocked(message) => Err(ApiError::Conflict { message, current: None }),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::models::{LoanStatus, ProductType, StatusCategory};
+        use rust_decimal::Decimal;
+
+        async fn export_csv(loans: Vec<Loan>) -> String {
+            let rows = futures_util::stream::iter(loans.into_iter().map(Ok));
+            let chunks: Vec<web::Bytes> =
+                encode_export(LoanFileFormat::Csv, rows).try_collect().await.unwrap();
+            String::from_utf8(chunks.concat()).unwrap()
+        }
+
+        #[actix_web::test]
+        async fn csv_export_without_loans_is_just_the_header() {
+            let csv = export_csv(Vec::new()).await;
+            assert_eq!(csv, format!("{}\n", Loan::CSV_HEADER.join(",")));
+        }
+
+        #[actix_web::test]
+        async fn csv_header_matches_the_serialized_fields() {
+            let loan = Loan {
+                loan_id: "LN-1".to_string(),
+                customer_name: "Doe, Jane".to_string(),
+                property_address: "1 Main St".to_string(),
+                origination_date: "2024-01-15".parse().unwrap(),
+                maturity_date: "2054-01-15".parse().unwrap(),
+                loan_amount: Decimal::new(20000000, 2),
+                remaining_balance: Decimal::new(20000000, 2),
+                interest_rate: Decimal::new(6000, 3),
+                monthly_payment: Decimal::new(119910, 2),
+                status: StatusCategory::Active,
+                product_name: "30 Year Fixed".to_string(),
+                product_type: ProductType::FixedRate,
+                security_name: "POOL-1".to_string(),
+                servicer_name: "Acme Servicing".to_string(),
+                current_status: LoanStatus::Current,
+                updated_at: None,
+            };
+            let mut expected = csv::Writer::from_writer(Vec::new());
+            expected.serialize(&loan).unwrap();
+            let expected = String::from_utf8(expected.into_inner().unwrap()).unwrap();
+
+            assert_eq!(export_csv(vec![loan]).await, expected);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,270 @@
+// src/health.rs
+//! Liveness and readiness probes. Liveness only shows that the process is serving requests;
+//! readiness checks the dependencies a request needs and answers 503 until they are usable.
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::pool::PoolConnection;
+use sqlx::{PgPool, Postgres};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use crate::MIGRATOR;
+
+/// How long each readiness check may take before its dependency is reported as down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Liveness {
+    status: HealthStatus,
+    timestamp: DateTime<Utc>,
+}
+
+/// `status` is `up` only when every check is.
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    status: HealthStatus,
+    timestamp: DateTime<Utc>,
+    checks: ReadinessChecks,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessChecks {
+    database: DatabaseCheck,
+    migrations: MigrationCheck,
+    pool: PoolCheck,
+}
+
+/// A `SELECT 1` round trip. When no connection can be acquired the check fails with the
+/// reason, timed from the start of the attempt.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseCheck {
+    status: HealthStatus,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The migrations recorded in the database compared with those built into this server.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationCheck {
+    status: HealthStatus,
+    /// Absent when no connection could be acquired to run the check on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Highest migration applied successfully.
+    applied_version: Option<i64>,
+    /// Highest migration built into this server.
+    expected_version: Option<i64>,
+    /// Built-in migrations the database has not applied.
+    pending: Vec<i64>,
+    /// Applied migrations that failed part-way or whose SQL differs from the built-in copy.
+    dirty: Vec<i64>,
+}
+
+/// Connection pool usage before the check. The pool is down when every connection is in use;
+/// the latency is the time taken to acquire one for the other checks.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolCheck {
+    status: HealthStatus,
+    latency_ms: f64,
+    size: u32,
+    idle: u32,
+    in_use: u32,
+    max_connections: u32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    operation_id = "liveness",
+    tag = "health",
+    security(()),
+    responses((status = 200, description = "The server is running", body = Liveness))
+)]
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(Liveness {
+        status: HealthStatus::Up,
+        timestamp: Utc::now(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    operation_id = "readiness",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Every dependency is usable", body = Readiness),
+        (status = 503, description = "At least one dependency is down", body = Readiness),
+    )
+)]
+pub async fn ready(pool: web::Data<PgPool>) -> HttpResponse {
+    let max_connections = pool.options().get_max_connections();
+    let size = pool.size();
+    let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX);
+    let in_use = size.saturating_sub(idle);
+
+    let (latency_ms, connection) = timed(pool.acquire()).await;
+    let pool_check = PoolCheck {
+        status: if in_use < max_connections { HealthStatus::Up } else { HealthStatus::Down },
+        latency_ms,
+        size,
+        idle,
+        in_use,
+        max_connections,
+    };
+
+    let (database, migrations) = match connection {
+        Ok(mut connection) => {
+            let database = check_database(&mut connection).await;
+            let migrations = check_migrations(&mut connection).await;
+            (database, migrations)
+        }
+        Err(error) => {
+            let database = DatabaseCheck {
+                status: HealthStatus::Down,
+                latency_ms,
+                error: Some(error),
+            };
+            let migrations = MigrationCheck {
+                status: HealthStatus::Down,
+                latency_ms: None,
+                error: Some("Not checked without a connection".to_string()),
+                applied_version: None,
+                expected_version: expected_version(),
+                pending: Vec::new(),
+                dirty: Vec::new(),
+            };
+            (database, migrations)
+        }
+    };
+
+    let ready = [database.status, migrations.status, pool_check.status]
+        .iter()
+        .all(|status| *status == HealthStatus::Up);
+    let readiness = Readiness {
+        status: if ready { HealthStatus::Up } else { HealthStatus::Down },
+        timestamp: Utc::now(),
+        checks: ReadinessChecks {
+            database,
+            migrations,
+            pool: pool_check,
+        },
+    };
+
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+async fn check_database(connection: &mut PoolConnection<Postgres>) -> DatabaseCheck {
+    let (latency_ms, result) = timed(sqlx::query("SELECT 1").execute(&mut **connection)).await;
+    DatabaseCheck {
+        status: if result.is_ok() { HealthStatus::Up } else { HealthStatus::Down },
+        latency_ms,
+        error: result.err(),
+    }
+}
+
+async fn check_migrations(connection: &mut PoolConnection<Postgres>) -> MigrationCheck {
+    let query = sqlx::query_as::<_, (i64, bool, Vec<u8>)>(
+        "SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version",
+    );
+    let (latency_ms, result) = timed(query.fetch_all(&mut **connection)).await;
+    let mut check = MigrationCheck {
+        status: HealthStatus::Down,
+        latency_ms: Some(latency_ms),
+        error: None,
+        applied_version: None,
+        expected_version: expected_version(),
+        pending: Vec::new(),
+        dirty: Vec::new(),
+    };
+
+    let applied = match result {
+        Ok(applied) => applied,
+        Err(error) => {
+            check.error = Some(error);
+            return check;
+        }
+    };
+    let checksums: HashMap<i64, &[u8]> = MIGRATOR
+        .iter()
+        .map(|migration| (migration.version, migration.checksum.as_ref()))
+        .collect();
+
+    check.applied_version = applied
+        .iter()
+        .filter(|(_, success, _)| *success)
+        .map(|(version, _, _)| *version)
+        .max();
+    check.pending = MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| !applied.iter().any(|(applied, _, _)| applied == version))
+        .collect();
+    check.dirty = applied
+        .iter()
+        .filter(|(version, success, checksum)| {
+            !success || checksums.get(version).is_some_and(|expected| expected != checksum)
+        })
+        .map(|(version, _, _)| *version)
+        .collect();
+
+    if check.pending.is_empty() && check.dirty.is_empty() {
+        check.status = HealthStatus::Up;
+    }
+    check
+}
+
+fn expected_version() -> Option<i64> {
+    MIGRATOR.iter().map(|migration| migration.version).max()
+}
+
+/// Runs a check under [`CHECK_TIMEOUT`], returning its latency in milliseconds. Failures
+/// are logged in full but only summarized in the response, which needs no token to read.
+async fn timed<T>(
+    check: impl Future<Output = Result<T, sqlx::Error>>,
+) -> (f64, Result<T, String>) {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(error)) => {
+            tracing::warn!("Readiness check failed: {}", error);
+            Err(describe(&error))
+        }
+        Err(_) => Err(format!("Timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    let latency_ms = (started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0;
+    (latency_ms, result)
+}
+
+fn describe(error: &sqlx::Error) -> String {
+    match error {
+        sqlx::Error::Database(db_error) => match db_error.code() {
+            Some(code) => format!("Database error {}", code),
+            None => "Database error".to_string(),
+        },
+        sqlx::Error::PoolTimedOut => "Timed out waiting for a connection".to_string(),
+        sqlx::Error::PoolClosed => "The connection pool is closed".to_string(),
+        _ => "Could not reach the database".to_string(),
+    }
+}
@@ -1,20 +1,39 @@
 // src/main.rs
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer, middleware::Logger};
+use actix_web::middleware::DefaultHeaders;
+use actix_web::{http::header, web, App, HttpServer};
 use dotenv::dotenv;
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use futures_util::future;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgPoolOptions;
 use std::env;
+use tracing_actix_web::TracingLogger;
+use utoipa_swagger_ui::SwaggerUi;
 
+mod auth;
+mod errors;
+mod health;
+mod metrics;
 mod models;
+mod money;
 mod handlers;
+mod openapi;
 mod services;
+mod telemetry;
 
+use auth::{AuthConfig, JwtAuth, RequireRole, Role};
+use errors::CorrelationId;
+use metrics::RequestMetrics;
 use handlers::loan_handlers;
+use telemetry::{RequestSpan, Telemetry};
+
+/// The migrations built into the server, applied at startup and compared by readiness checks.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    env_logger::init();
+    let telemetry = Telemetry::init().expect("Failed to set up logging and tracing");
 
     let database_url = env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set");
@@ -26,43 +45,139 @@ async fn main() -> std::io::Result<()> {
         .expect("Failed to create database pool");
 
     // Run migrations
-    sqlx::migrate!("./migrations")
+    MIGRATOR
         .run(&pool)
         .await
         .expect("Failed to run migrations");
 
+    let auth_config = web::Data::new(
+        AuthConfig::from_env().expect("Invalid JWT configuration"),
+    );
+
+    // Comma-separated list of origins allowed to call the API from a browser
+    let allowed_origins: Vec<String> = env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_else(|_| "http://localhost:4200".to_string())
+        .split(',')
+        .map(|origin| origin.trim().to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect();
+
     let bind_address = env::var("BIND_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+    // Metrics expose loan counts without a token, so they get a listener of their own that
+    // only the monitoring network should reach.
+    let metrics_address =
+        env::var("METRICS_BIND_ADDRESS").unwrap_or_else(|_| "127.0.0.1:9090".to_string());
+
+    let api_doc = openapi::document();
 
-    log::info!("Starting server at http://{}", bind_address);
+    tracing::info!("Starting server at http://{}", bind_address);
+    tracing::info!("Serving metrics at http://{}/metrics", metrics_address);
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
+    let metrics_pool = web::Data::new(pool.clone());
+    let metrics_server = HttpServer::new(move || {
+        App::new()
+            .app_data(metrics_pool.clone())
+            .route("/metrics", web::get().to(metrics::export))
+    })
+    .workers(1)
+    .bind(&metrics_address)?
+    .run();
+
+    let api_server = HttpServer::new(move || {
+        let cors = allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+            .allowed_headers(vec![
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::ACCEPT,
+                header::HeaderName::from_static("traceparent"),
+                header::HeaderName::from_static("tracestate"),
+            ])
             .max_age(3600);
 
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(auth_config.clone())
+            .app_data(web::QueryConfig::default().error_handler(errors::query_error))
+            .app_data(web::JsonConfig::default().error_handler(errors::json_error))
+            .app_data(web::PathConfig::default().error_handler(errors::path_error))
+            .wrap(CorrelationId)
             .wrap(cors)
-            .wrap(Logger::default())
+            .wrap(TracingLogger::<RequestSpan>::new())
+            .wrap(RequestMetrics)
+            .service(web::scope("/api/v1/loans").wrap(JwtAuth).configure(loan_routes))
+            // Unversioned paths from before /api/v1, kept until clients have moved over.
             .service(
                 web::scope("/api/loans")
-                    .route("/search", web::get().to(loan_handlers::search_loans))
-                    .route("/{loan_id}", web::get().to(loan_handlers::get_loan_by_id))
-                    .route("/statistics", web::get().to(loan_handlers::get_statistics))
+                    .wrap(JwtAuth)
+                    .wrap(
+                        DefaultHeaders::new()
+                            .add(("Deprecation", "true"))
+                            .add((header::LINK, r#"</api/v1/loans>; rel="successor-version""#)),
+                    )
+                    .configure(loan_routes),
             )
-            .route("/health", web::get().to(health_check))
+            .service(
+                SwaggerUi::new("/swagger-ui/{_:.*}")
+                    .url("/api-docs/openapi.json", api_doc.clone()),
+            )
+            .service(
+                web::scope("/health")
+                    .route("/live", web::get().to(health::live))
+                    .route("/ready", web::get().to(health::ready)),
+            )
+            .default_service(web::to(errors::route_not_found))
     })
     .bind(&bind_address)?
-    .run()
-    .await
-}
+    .run();
 
-async fn health_check() -> actix_web::Result<impl actix_web::Responder> {
-    Ok(web::Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now()
-    })))
+    let result = future::try_join(api_server, metrics_server).await.map(|_| ());
+
+    telemetry.shutdown();
+    result
 }
 
+/// The loan API, mounted under `/api/v1/loans` and the deprecated `/api/loans`.
+fn loan_routes(cfg: &mut web::ServiceConfig) {
+    let viewer = || RequireRole(Role::Viewer);
+    let servicer = || RequireRole(Role::Servicer);
+    let admin = || RequireRole(Role::Admin);
+
+    cfg.route("", web::post().to(loan_handlers::create_loan).wrap(servicer()))
+        .route("/search", web::get().to(loan_handlers::search_loans).wrap(viewer()))
+        .route("/query", web::post().to(loan_handlers::query_loans).wrap(viewer()))
+        .route("/export", web::get().to(loan_handlers::export_loans).wrap(viewer()))
+        .route("/import", web::post().to(loan_handlers::import_loans).wrap(servicer()))
+        .route("/statistics", web::get().to(loan_handlers::get_statistics).wrap(viewer()))
+        .route("/trends/{metric}", web::get().to(loan_handlers::get_trend).wrap(viewer()))
+        .route("/{loan_id}", web::get().to(loan_handlers::get_loan_by_id).wrap(viewer()))
+        .route("/{loan_id}", web::put().to(loan_handlers::update_loan).wrap(servicer()))
+        .route("/{loan_id}", web::patch().to(loan_handlers::patch_loan).wrap(servicer()))
+        .route("/{loan_id}", web::delete().to(loan_handlers::delete_loan).wrap(admin()))
+        .route(
+            "/{loan_id}/payments",
+            web::post().to(loan_handlers::post_payment).wrap(servicer()),
+        )
+        .route(
+            "/{loan_id}/payments",
+            web::get().to(loan_handlers::get_payments).wrap(viewer()),
+        )
+        .route(
+            "/{loan_id}/schedule",
+            web::get().to(loan_handlers::get_schedule).wrap(viewer()),
+        )
+        .route(
+            "/{loan_id}/status",
+            web::post().to(loan_handlers::transition_status).wrap(servicer()),
+        )
+        .route(
+            "/{loan_id}/status-history",
+            web::get().to(loan_handlers::get_status_history).wrap(viewer()),
+        )
+        .route(
+            "/{loan_id}/audit",
+            web::get().to(loan_handlers::get_audit_trail).wrap(admin()),
+        );
+}
@@ -0,0 +1,223 @@
+// src/metrics.rs
+//! Prometheus metrics served at `/metrics` on a listener of their own: requests per route,
+//! time spent in each `LoanService` method, connection pool usage and loans by status.
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error, HttpResponse};
+use anyhow::Context;
+use futures_util::future::LocalBoxFuture;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+use sqlx::PgPool;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::errors::ApiError;
+use crate::models::StatusCategory;
+use crate::services::LoanService;
+
+/// Route label for requests that matched no route, so unknown paths cannot grow the
+/// number of series without bound. Nonstandard methods are likewise labelled `OTHER`.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+const LOAN_COUNT_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    query_duration: HistogramVec,
+    calls_in_flight: IntGauge,
+    pool_size: IntGauge,
+    pool_idle: IntGauge,
+    pool_waiting: IntGauge,
+    pool_max: IntGauge,
+    loans: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("loan_api".to_string()), None)?;
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by route and status"),
+                &["method", "route", "status"],
+            )?,
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time until the response headers were sent, by route",
+                ),
+                &["method", "route"],
+            )?,
+            query_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "db_query_duration_seconds",
+                    "Time spent in each LoanService method, including waiting for a connection",
+                ),
+                &["method"],
+            )?,
+            calls_in_flight: IntGauge::new("db_calls_in_flight", "LoanService calls in progress")?,
+            pool_size: IntGauge::new("db_pool_size", "Open connections in the pool")?,
+            pool_idle: IntGauge::new("db_pool_idle", "Open connections not in use")?,
+            pool_waiting: IntGauge::new(
+                "db_pool_waiting",
+                "LoanService calls in progress beyond the connections in use, an estimate of \
+                 those waiting for a connection",
+            )?,
+            pool_max: IntGauge::new("db_pool_max_connections", "Size limit of the pool")?,
+            loans: IntGaugeVec::new(Opts::new("loans", "Loans by status"), &["status"])?,
+            registry,
+        };
+
+        metrics.registry.register(Box::new(metrics.http_requests.clone()))?;
+        metrics.registry.register(Box::new(metrics.http_request_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.query_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.calls_in_flight.clone()))?;
+        metrics.registry.register(Box::new(metrics.pool_size.clone()))?;
+        metrics.registry.register(Box::new(metrics.pool_idle.clone()))?;
+        metrics.registry.register(Box::new(metrics.pool_waiting.clone()))?;
+        metrics.registry.register(Box::new(metrics.pool_max.clone()))?;
+        metrics.registry.register(Box::new(metrics.loans.clone()))?;
+        Ok(metrics)
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| Metrics::new().expect("Metric definitions are valid and unique"))
+}
+
+/// Times a `LoanService` call from its creation until it is dropped, so calls that fail or
+/// are cancelled are counted too.
+pub struct QueryTimer {
+    _timer: HistogramTimer,
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        metrics().calls_in_flight.dec();
+    }
+}
+
+pub fn query_timer(method: &'static str) -> QueryTimer {
+    let metrics = metrics();
+    metrics.calls_in_flight.inc();
+    QueryTimer {
+        _timer: metrics.query_duration.with_label_values(&[method]).start_timer(),
+    }
+}
+
+/// Serves every metric in the Prometheus text format on the metrics listener. The pool and
+/// loan gauges are read at scrape time; if the loans cannot be counted in time, say because
+/// the pool is exhausted, their series are left out rather than stale and the rest is still
+/// served.
+pub async fn export(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let metrics = metrics();
+
+    let size = pool.size();
+    let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX);
+    let in_use = i64::from(size.saturating_sub(idle));
+    metrics.pool_size.set(i64::from(size));
+    metrics.pool_idle.set(i64::from(idle));
+    metrics.pool_waiting.set((metrics.calls_in_flight.get() - in_use).max(0));
+    metrics.pool_max.set(i64::from(pool.options().get_max_connections()));
+
+    metrics.loans.reset();
+    let counts = tokio::time::timeout(LOAN_COUNT_TIMEOUT, LoanService::count_by_status(&pool));
+    match counts.await {
+        Ok(Ok(counts)) => {
+            for status in StatusCategory::ALL {
+                metrics.loans.with_label_values(&[status.as_str()]).set(0);
+            }
+            for count in counts {
+                metrics.loans.with_label_values(&[&count.status]).set(count.count);
+            }
+        }
+        Ok(Err(error)) => tracing::warn!("Failed to count loans for metrics: {:#}", error),
+        Err(_) => tracing::warn!("Timed out counting loans for metrics"),
+    }
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&metrics.registry.gather(), &mut body)
+        .context("Failed to encode metrics")?;
+    Ok(HttpResponse::Ok().content_type(encoder.format_type()).body(body))
+}
+
+/// Counts and times every request by method, matched route pattern and status.
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let method = match *req.method() {
+            Method::GET => "GET",
+            Method::POST => "POST",
+            Method::PUT => "PUT",
+            Method::PATCH => "PATCH",
+            Method::DELETE => "DELETE",
+            Method::HEAD => "HEAD",
+            Method::OPTIONS => "OPTIONS",
+            _ => "OTHER",
+        };
+        let started = Instant::now();
+
+        Box::pin(async move {
+            let result = service.call(req).await;
+            let (route, status) = match &result {
+                Ok(res) => (res.request().match_pattern(), res.status()),
+                Err(error) => (None, error.as_response_error().status_code()),
+            };
+            let route = route.unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+            let metrics = metrics();
+            metrics
+                .http_requests
+                .with_label_values(&[method, &route, status.as_str()])
+                .inc();
+            metrics
+                .http_request_duration
+                .with_label_values(&[method, &route])
+                .observe(started.elapsed().as_secs_f64());
+            result
+        })
+    }
+}
@@ -1,9 +1,15 @@
 // src/models.rs
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, NaiveDate};
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
 use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::money;
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Loan {
     pub loan_id: String,
@@ -11,58 +17,1739 @@ pub struct Loan {
     pub property_address: String,
     pub origination_date: NaiveDate,
     pub maturity_date: NaiveDate,
-    pub loan_amount: f64,
-    pub remaining_balance: f64,
-    pub interest_rate: f64,
-    pub monthly_payment: f64,
-    pub status: String,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub loan_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub remaining_balance: Decimal,
+    #[serde(serialize_with = "money::serialize_rate")]
+    pub interest_rate: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub monthly_payment: Decimal,
+    pub status: StatusCategory,
     pub product_name: String,
-    pub product_type: String,
+    pub product_type: ProductType,
     pub security_name: String,
     pub servicer_name: String,
-    pub current_status: String,
+    pub current_status: LoanStatus,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Loan {
+    /// Header record of a CSV export, in the order the fields are serialized.
+    pub const CSV_HEADER: [&'static str; 16] = [
+        "loanId",
+        "customerName",
+        "propertyAddress",
+        "originationDate",
+        "maturityDate",
+        "loanAmount",
+        "remainingBalance",
+        "interestRate",
+        "monthlyPayment",
+        "status",
+        "productName",
+        "productType",
+        "securityName",
+        "servicerName",
+        "currentStatus",
+        "updatedAt",
+    ];
+}
+
+/// Editable loan fields shared by create, replace and patch requests.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanFields {
+    pub customer_name: String,
+    pub property_address: String,
+    pub origination_date: NaiveDate,
+    pub maturity_date: NaiveDate,
+    pub loan_amount: Decimal,
+    pub remaining_balance: Decimal,
+    pub interest_rate: Decimal,
+    pub monthly_payment: Decimal,
+    pub status: StatusCategory,
+    pub product_name: String,
+    pub product_type: ProductType,
+    pub security_name: String,
+    pub servicer_name: String,
+    pub current_status: LoanStatus,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl LoanFields {
+    /// Checks the fields against the constraints of the `loans` table.
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        // Length limits follow the VARCHAR sizes of each column.
+        let text_fields = [
+            ("customerName", self.customer_name.as_str(), 255),
+            ("propertyAddress", self.property_address.as_str(), usize::MAX),
+            ("productName", self.product_name.as_str(), 255),
+            ("securityName", self.security_name.as_str(), 255),
+            ("servicerName", self.servicer_name.as_str(), 255),
+        ];
+        for (field, value, max_len) in text_fields {
+            if value.trim().is_empty() {
+                errors.push(FieldError { field, message: "must not be empty".to_string() });
+            } else if value.chars().count() > max_len {
+                errors.push(FieldError {
+                    field,
+                    message: format!("must be at most {} characters", max_len),
+                });
+            }
+        }
+
+        // Upper bounds and decimal places follow the DECIMAL precision of each column.
+        let amount_fields = [
+            ("loanAmount", self.loan_amount, 10_000_000_000_000_i64, money::MONEY_SCALE),
+            ("remainingBalance", self.remaining_balance, 10_000_000_000_000, money::MONEY_SCALE),
+            ("monthlyPayment", self.monthly_payment, 100_000_000, money::MONEY_SCALE),
+            ("interestRate", self.interest_rate, 100, money::RATE_SCALE),
+        ];
+        for (field, value, upper, scale) in amount_fields {
+            if value.is_sign_negative() && !value.is_zero() {
+                errors.push(FieldError { field, message: "must be a non-negative number".to_string() });
+            } else if value >= Decimal::from(upper) {
+                errors.push(FieldError { field, message: format!("must be less than {}", upper) });
+            } else if !money::has_scale(value, scale) {
+                errors.push(FieldError {
+                    field,
+                    message: format!("must have at most {} decimal places", scale),
+                });
+            }
+        }
+
+        if self.remaining_balance > self.loan_amount {
+            errors.push(FieldError {
+                field: "remainingBalance",
+                message: "must not exceed loanAmount".to_string(),
+            });
+        }
+
+        if self.current_status.category() != self.status {
+            errors.push(FieldError {
+                field: "status",
+                message: format!(
+                    "must be {} when currentStatus is {}",
+                    self.current_status.category().as_str(),
+                    self.current_status.as_str()
+                ),
+            });
+        }
+
+        if self.maturity_date <= self.origination_date {
+            errors.push(FieldError {
+                field: "maturityDate",
+                message: "must be after originationDate".to_string(),
+            });
+        }
+
+        errors
+    }
+}
+
+/// Servicing lifecycle of a loan, stored in `current_status`. Each state belongs to one
+/// [`StatusCategory`], which is stored alongside it in `status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+pub enum LoanStatus {
+    Current,
+    #[serde(rename = "30 Days Late")]
+    #[sqlx(rename = "30 Days Late")]
+    ThirtyDaysLate,
+    #[serde(rename = "60 Days Late")]
+    #[sqlx(rename = "60 Days Late")]
+    SixtyDaysLate,
+    #[serde(rename = "90 Days Late")]
+    #[sqlx(rename = "90 Days Late")]
+    NinetyDaysLate,
+    Default,
+    #[serde(rename = "In Foreclosure")]
+    #[sqlx(rename = "In Foreclosure")]
+    InForeclosure,
+    #[serde(rename = "Paid Off")]
+    #[sqlx(rename = "Paid Off")]
+    PaidOff,
+    #[serde(rename = "Charged Off")]
+    #[sqlx(rename = "Charged Off")]
+    ChargedOff,
+}
+
+impl LoanStatus {
+    pub const ALL: [LoanStatus; 8] = [
+        LoanStatus::Current,
+        LoanStatus::ThirtyDaysLate,
+        LoanStatus::SixtyDaysLate,
+        LoanStatus::NinetyDaysLate,
+        LoanStatus::Default,
+        LoanStatus::InForeclosure,
+        LoanStatus::PaidOff,
+        LoanStatus::ChargedOff,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LoanStatus::Current => "Current",
+            LoanStatus::ThirtyDaysLate => "30 Days Late",
+            LoanStatus::SixtyDaysLate => "60 Days Late",
+            LoanStatus::NinetyDaysLate => "90 Days Late",
+            LoanStatus::Default => "Default",
+            LoanStatus::InForeclosure => "In Foreclosure",
+            LoanStatus::PaidOff => "Paid Off",
+            LoanStatus::ChargedOff => "Charged Off",
+        }
+    }
+
+    pub fn category(self) -> StatusCategory {
+        match self {
+            LoanStatus::Current => StatusCategory::Active,
+            LoanStatus::ThirtyDaysLate
+            | LoanStatus::SixtyDaysLate
+            | LoanStatus::NinetyDaysLate => StatusCategory::Delinquent,
+            LoanStatus::Default | LoanStatus::InForeclosure => StatusCategory::Default,
+            LoanStatus::PaidOff => StatusCategory::PaidOff,
+            LoanStatus::ChargedOff => StatusCategory::ChargedOff,
+        }
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, LoanStatus::PaidOff | LoanStatus::ChargedOff)
+    }
+
+    /// Delinquency can roll forward one bucket at a time or cure back to any earlier
+    /// bucket; defaulted loans can be reinstated, foreclosed, paid off or charged off.
+    pub fn can_transition_to(self, next: LoanStatus) -> bool {
+        use LoanStatus::*;
+
+        match (self, next) {
+            (from, to) if from == to => false,
+            (PaidOff | ChargedOff, _) => false,
+            (_, PaidOff) => true,
+            (Current, ThirtyDaysLate) => true,
+            (ThirtyDaysLate, Current | SixtyDaysLate) => true,
+            (SixtyDaysLate, Current | ThirtyDaysLate | NinetyDaysLate) => true,
+            (NinetyDaysLate, Current | ThirtyDaysLate | SixtyDaysLate | Default) => true,
+            (Default, Current | InForeclosure | ChargedOff) => true,
+            (InForeclosure, Current | ChargedOff) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Coarse state of a loan, stored in `status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+pub enum StatusCategory {
+    Active,
+    Delinquent,
+    Default,
+    #[serde(rename = "Paid Off")]
+    #[sqlx(rename = "Paid Off")]
+    PaidOff,
+    #[serde(rename = "Charged Off")]
+    #[sqlx(rename = "Charged Off")]
+    ChargedOff,
+}
+
+impl StatusCategory {
+    pub const ALL: [StatusCategory; 5] = [
+        StatusCategory::Active,
+        StatusCategory::Delinquent,
+        StatusCategory::Default,
+        StatusCategory::PaidOff,
+        StatusCategory::ChargedOff,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatusCategory::Active => "Active",
+            StatusCategory::Delinquent => "Delinquent",
+            StatusCategory::Default => "Default",
+            StatusCategory::PaidOff => "Paid Off",
+            StatusCategory::ChargedOff => "Charged Off",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+pub enum ProductType {
+    #[serde(rename = "Fixed Rate")]
+    #[sqlx(rename = "Fixed Rate")]
+    FixedRate,
+    #[serde(rename = "Adjustable Rate")]
+    #[sqlx(rename = "Adjustable Rate")]
+    AdjustableRate,
+    #[serde(rename = "Interest Only")]
+    #[sqlx(rename = "Interest Only")]
+    InterestOnly,
+}
+
+impl ProductType {
+    pub const ALL: [ProductType; 3] =
+        [ProductType::FixedRate, ProductType::AdjustableRate, ProductType::InterestOnly];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProductType::FixedRate => "Fixed Rate",
+            ProductType::AdjustableRate => "Adjustable Rate",
+            ProductType::InterestOnly => "Interest Only",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTransitionRequest {
+    pub status: LoanStatus,
+    pub reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanStatusChange {
+    pub history_id: i64,
+    pub loan_id: String,
+    pub from_status: Option<String>,
+    pub from_current_status: Option<String>,
+    pub to_status: String,
+    pub to_current_status: String,
+    pub reason: Option<String>,
+    pub changed_by: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub audit_id: i64,
+    pub loan_id: String,
+    pub action: String,
+    pub field_name: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_by: String,
+    pub transaction_id: i64,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilters {
+    pub changed_by: Option<String>,
+    pub changed_from: Option<DateTime<Utc>>,
+    pub changed_to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLoanRequest {
+    pub loan_id: String,
+    #[serde(flatten)]
+    pub fields: LoanFields,
+}
+
+/// Full replacement of a loan. `updated_at` must match the stored value.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateLoanRequest {
+    #[serde(flatten)]
+    pub fields: LoanFields,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Partial update of a loan. `updated_at` must match the stored value.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchLoanRequest {
+    pub customer_name: Option<String>,
+    pub property_address: Option<String>,
+    pub origination_date: Option<NaiveDate>,
+    pub maturity_date: Option<NaiveDate>,
+    pub loan_amount: Option<Decimal>,
+    pub remaining_balance: Option<Decimal>,
+    pub interest_rate: Option<Decimal>,
+    pub monthly_payment: Option<Decimal>,
+    pub status: Option<StatusCategory>,
+    pub product_name: Option<String>,
+    pub product_type: Option<ProductType>,
+    pub security_name: Option<String>,
+    pub servicer_name: Option<String>,
+    pub current_status: Option<LoanStatus>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatchLoanRequest {
+    pub fn apply_to(self, loan: Loan) -> LoanFields {
+        LoanFields {
+            customer_name: self.customer_name.unwrap_or(loan.customer_name),
+            property_address: self.property_address.unwrap_or(loan.property_address),
+            origination_date: self.origination_date.unwrap_or(loan.origination_date),
+            maturity_date: self.maturity_date.unwrap_or(loan.maturity_date),
+            loan_amount: self.loan_amount.unwrap_or(loan.loan_amount),
+            remaining_balance: self.remaining_balance.unwrap_or(loan.remaining_balance),
+            interest_rate: self.interest_rate.unwrap_or(loan.interest_rate),
+            monthly_payment: self.monthly_payment.unwrap_or(loan.monthly_payment),
+            status: self.status.unwrap_or(loan.status),
+            product_name: self.product_name.unwrap_or(loan.product_name),
+            product_type: self.product_type.unwrap_or(loan.product_type),
+            security_name: self.security_name.unwrap_or(loan.security_name),
+            servicer_name: self.servicer_name.unwrap_or(loan.servicer_name),
+            current_status: self.current_status.unwrap_or(loan.current_status),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteLoanParams {
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchFilters {
     pub customer_name: Option<String>,
-    pub status: Option<String>,
-    pub product_type: Option<String>,
+    #[param(inline)]
+    pub status: Option<StatusCategory>,
+    #[param(inline)]
+    pub product_type: Option<ProductType>,
     pub servicer_name: Option<String>,
-    pub min_loan_amount: Option<f64>,
-    pub max_loan_amount: Option<f64>,
+    pub min_loan_amount: Option<Decimal>,
+    pub max_loan_amount: Option<Decimal>,
     pub origination_date_from: Option<NaiveDate>,
     pub origination_date_to: Option<NaiveDate>,
+    /// Full-text search over customer name, property address, product and security name.
+    pub q: Option<String>,
+    /// Typo-tolerant match on customer name or property address, e.g. `Jon Smtih`.
+    pub similar: Option<String>,
+    /// Word similarity (0 to 1) a `similar` match needs; defaults to 0.3.
+    pub min_similarity: Option<f32>,
+    pub page: Option<i32>,
+    pub page_size: Option<i32>,
+    /// Opaque `nextCursor`/`prevCursor` from a previous response; takes precedence over `page`.
+    pub cursor: Option<String>,
+    /// Comma-separated columns, `-` prefix for descending, e.g. `-loan_amount,origination_date`.
+    pub sort: Option<String>,
+}
+
+impl SearchFilters {
+    /// The equivalent filter tree, so query-string searches share `/api/v1/loans/query`'s path.
+    pub fn into_query(self) -> LoanQuery {
+        let mut filters = Vec::new();
+
+        if let Some(customer_name) = self.customer_name {
+            filters.push(LoanFilter::Contains {
+                column: LoanColumn::CustomerName,
+                value: customer_name,
+            });
+        }
+        if let Some(status) = self.status {
+            filters.push(LoanFilter::Eq {
+                column: LoanColumn::Status,
+                value: status.as_str().into(),
+            });
+        }
+        if let Some(product_type) = self.product_type {
+            filters.push(LoanFilter::Eq {
+                column: LoanColumn::ProductType,
+                value: product_type.as_str().into(),
+            });
+        }
+        if let Some(servicer_name) = self.servicer_name {
+            filters.push(LoanFilter::Contains {
+                column: LoanColumn::ServicerName,
+                value: servicer_name,
+            });
+        }
+        if self.min_loan_amount.is_some() || self.max_loan_amount.is_some() {
+            filters.push(LoanFilter::Range {
+                column: LoanColumn::LoanAmount,
+                min: self.min_loan_amount.map(|amount| amount.to_string().into()),
+                max: self.max_loan_amount.map(|amount| amount.to_string().into()),
+            });
+        }
+        if self.origination_date_from.is_some() || self.origination_date_to.is_some() {
+            filters.push(LoanFilter::Range {
+                column: LoanColumn::OriginationDate,
+                min: self.origination_date_from.map(|date| date.to_string().into()),
+                max: self.origination_date_to.map(|date| date.to_string().into()),
+            });
+        }
+
+        LoanQuery {
+            filter: (!filters.is_empty()).then_some(LoanFilter::And(filters)),
+            q: self.q,
+            similar: self.similar,
+            min_similarity: self.min_similarity,
+            sort: self.sort,
+            page: self.page,
+            page_size: self.page_size,
+            cursor: self.cursor,
+        }
+    }
+}
+
+/// File format of loan exports and imports.
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LoanFileFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+impl LoanFileFormat {
+    /// Recognises an uploaded file by its content type, then by its extension.
+    pub fn detect(content_type: Option<&str>, filename: Option<&str>) -> Option<LoanFileFormat> {
+        match content_type {
+            Some("text/csv") => return Some(LoanFileFormat::Csv),
+            Some("application/x-ndjson") => return Some(LoanFileFormat::Ndjson),
+            _ => {}
+        }
+
+        let extension = filename?.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(LoanFileFormat::Csv),
+            "ndjson" | "jsonl" => Some(LoanFileFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    #[serde(default)]
+    #[param(inline)]
+    pub format: LoanFileFormat,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// Validate the file and report what would change without writing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Overrides the format detected from the uploaded file.
+    #[param(inline)]
+    pub format: Option<LoanFileFormat>,
+}
+
+/// The multipart form `POST /api/v1/loans/import` reads, as described in the OpenAPI
+/// document; the handler streams the field instead of deserializing into this.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct ImportUpload {
+    /// A CSV or NDJSON loan tape.
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+/// A data row of an uploaded loan tape, parsed into a create request where possible.
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Line of the file the row starts on.
+    pub line: u64,
+    pub loan: Result<CreateLoanRequest, FieldError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoanIdColumn {
+    loan_id: String,
+}
+
+impl ImportRow {
+    /// Splits a loan tape into rows. CSV files need a header row naming the `Loan` fields
+    /// as exported (`loanId`, `customerName`, ...); extra columns such as `updatedAt` are ignored.
+    pub fn parse_all(bytes: &[u8], format: LoanFileFormat) -> Vec<ImportRow> {
+        match format {
+            LoanFileFormat::Csv => Self::parse_csv(bytes),
+            LoanFileFormat::Ndjson => Self::parse_ndjson(bytes),
+        }
+    }
+
+    fn parse_csv(bytes: &[u8]) -> Vec<ImportRow> {
+        let unreadable = |line: u64, e: csv::Error| ImportRow {
+            line,
+            loan: Err(FieldError { field: "row", message: e.to_string() }),
+        };
+
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(bytes);
+        let headers = match reader.headers() {
+            Ok(headers) => headers.clone(),
+            Err(e) => return vec![unreadable(1, e)],
+        };
+
+        // Line numbers are counted from byte offsets because the reader's own count is off by
+        // one for CRLF files, whose records it places at the `\n` ending the previous line.
+        let mut counted = (0, 1);
+        let mut line_at = |position: Option<&csv::Position>| {
+            let end = position.map_or(0, |position| position.byte() as usize + 1);
+            let byte = end.min(bytes.len());
+            let (from, line) = counted;
+            if byte > from {
+                counted = (byte, line + bytes[from..byte].iter().filter(|&&b| b == b'\n').count());
+            }
+            counted.1 as u64
+        };
+
+        reader
+            .records()
+            .map(|record| {
+                let record = match record {
+                    Ok(record) => record,
+                    Err(e) => {
+                        let line = line_at(e.position());
+                        return unreadable(line, e);
+                    }
+                };
+                let line = line_at(record.position());
+
+                // Deserialised twice because the csv crate cannot fill `#[serde(flatten)]` fields.
+                let loan = record
+                    .deserialize::<LoanIdColumn>(Some(&headers))
+                    .and_then(|id| {
+                        let fields = record.deserialize::<LoanFields>(Some(&headers))?;
+                        Ok(CreateLoanRequest { loan_id: id.loan_id, fields })
+                    })
+                    .map_err(|e| FieldError { field: "row", message: e.to_string() });
+
+                ImportRow { line, loan }
+            })
+            .collect()
+    }
+
+    fn parse_ndjson(bytes: &[u8]) -> Vec<ImportRow> {
+        bytes
+            .split(|&byte| byte == b'\n')
+            .zip(1..)
+            .filter(|(line, _)| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|(line, number)| ImportRow {
+                line: number,
+                loan: serde_json::from_slice(line)
+                    .map_err(|e| FieldError { field: "row", message: e.to_string() }),
+            })
+            .collect()
+    }
+}
+
+/// Outcome of an import. Counts describe what was written, or would be for a dry run.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    pub line: u64,
+    pub loan_id: Option<String>,
+    pub errors: Vec<FieldError>,
+}
+
+/// Body of `POST /api/v1/loans/query`; paged and sorted like the query-string search.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanQuery {
+    pub filter: Option<LoanFilter>,
+    pub q: Option<String>,
+    pub similar: Option<String>,
+    pub min_similarity: Option<f32>,
+    pub sort: Option<String>,
     pub page: Option<i32>,
     pub page_size: Option<i32>,
+    pub cursor: Option<String>,
+}
+
+impl LoanQuery {
+    pub const DEFAULT_PAGE_SIZE: i32 = 50;
+    pub const MAX_PAGE_SIZE: i32 = 1000;
+    pub const DEFAULT_MIN_SIMILARITY: f32 = 0.3;
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if self.page.is_some_and(|page| page < 1) {
+            errors.push(FieldError { field: "page", message: "must be at least 1".to_string() });
+        }
+
+        if self
+            .page_size
+            .is_some_and(|page_size| !(1..=Self::MAX_PAGE_SIZE).contains(&page_size))
+        {
+            errors.push(FieldError {
+                field: "page_size",
+                message: format!("must be between 1 and {}", Self::MAX_PAGE_SIZE),
+            });
+        }
+
+        let sort = match self.sort_keys() {
+            Ok(sort) => Some(sort),
+            Err(error) => {
+                errors.push(error);
+                None
+            }
+        };
+
+        if let Some(cursor) = &self.cursor {
+            match (SearchCursor::decode(cursor), sort) {
+                (None, _) => errors.push(FieldError {
+                    field: "cursor",
+                    message: "is not a valid cursor".to_string(),
+                }),
+                (Some(cursor), Some(sort)) if cursor.sort != SortKey::spec(&sort) => {
+                    errors.push(FieldError {
+                        field: "cursor",
+                        message: "was issued for a different sort".to_string(),
+                    })
+                }
+                (Some(cursor), Some(sort)) if !cursor.fits(&sort) => {
+                    errors.push(FieldError {
+                        field: "cursor",
+                        message: "does not hold a value of the right type for each sort column"
+                            .to_string(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        if self.q.as_deref().is_some_and(|q| q.trim().is_empty()) {
+            errors.push(FieldError { field: "q", message: "must not be empty".to_string() });
+        }
+
+        if self.similar.as_deref().is_some_and(|similar| similar.trim().is_empty()) {
+            errors.push(FieldError { field: "similar", message: "must not be empty".to_string() });
+        }
+
+        if self.min_similarity.is_some_and(|min| !(0.0..=1.0).contains(&min)) {
+            errors.push(FieldError {
+                field: "min_similarity",
+                message: "must be between 0 and 1".to_string(),
+            });
+        }
+
+        if self.ranked() && self.cursor.is_some() {
+            errors.push(FieldError {
+                field: "cursor",
+                message: "is not supported for results ranked by q or similar; use page".to_string(),
+            });
+        }
+
+        if let Some(filter) = &self.filter {
+            filter.validate(0, &mut errors);
+        }
+
+        errors
+    }
+
+    /// Whether results are ordered by `q` relevance or `similar` score rather than by `sort`.
+    pub fn ranked(&self) -> bool {
+        (self.q.is_some() || self.similar.is_some()) && self.sort.is_none()
+    }
+
+    /// The requested ordering, always ending with `loan_id` so that rows have a stable order.
+    pub fn sort_keys(&self) -> Result<Vec<SortKey>, FieldError> {
+        let mut keys: Vec<SortKey> = Vec::new();
+
+        for part in self.sort.as_deref().unwrap_or("").split(',').map(str::trim) {
+            if part.is_empty() {
+                continue;
+            }
+            let (descending, name) = match part.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, part.strip_prefix('+').unwrap_or(part)),
+            };
+            let column = LoanColumn::from_column(name).ok_or_else(|| FieldError {
+                field: "sort",
+                message: format!(
+                    "unknown column {}; allowed: {}",
+                    name,
+                    LoanColumn::ALL.map(LoanColumn::column).join(", ")
+                ),
+            })?;
+            if keys.iter().any(|key| key.column == column) {
+                return Err(FieldError {
+                    field: "sort",
+                    message: format!("column {} is listed more than once", name),
+                });
+            }
+            keys.push(SortKey { column, descending });
+        }
+
+        if !keys.iter().any(|key| key.column == LoanColumn::LoanId) {
+            keys.push(SortKey { column: LoanColumn::LoanId, descending: false });
+        }
+
+        Ok(keys)
+    }
+}
+
+/// A boolean condition over loan columns, e.g.
+/// `{"and": [{"in": {"column": "status", "values": ["Delinquent", "Default"]}},
+/// {"not": {"eq": {"column": "servicer_name", "value": "ABC Servicing"}}}]}`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoanFilter {
+    #[schema(no_recursion)]
+    And(Vec<LoanFilter>),
+    #[schema(no_recursion)]
+    Or(Vec<LoanFilter>),
+    #[schema(no_recursion)]
+    Not(Box<LoanFilter>),
+    Eq {
+        column: LoanColumn,
+        value: serde_json::Value,
+    },
+    In {
+        column: LoanColumn,
+        values: Vec<serde_json::Value>,
+    },
+    /// Inclusive bounds; either may be left out.
+    Range {
+        column: LoanColumn,
+        min: Option<serde_json::Value>,
+        max: Option<serde_json::Value>,
+    },
+    /// Case-insensitive substring match on a text column.
+    Contains {
+        column: LoanColumn,
+        value: String,
+    },
+}
+
+impl LoanFilter {
+    pub const MAX_DEPTH: usize = 16;
+
+    fn validate(&self, depth: usize, errors: &mut Vec<FieldError>) {
+        let mut invalid = |message: String| errors.push(FieldError { field: "filter", message });
+
+        if depth >= Self::MAX_DEPTH {
+            invalid(format!("is nested more than {} levels deep", Self::MAX_DEPTH));
+            return;
+        }
+
+        match self {
+            LoanFilter::And(filters) | LoanFilter::Or(filters) => {
+                for filter in filters {
+                    filter.validate(depth + 1, errors);
+                }
+            }
+            LoanFilter::Not(filter) => filter.validate(depth + 1, errors),
+            LoanFilter::Eq { column, value } => {
+                if !column.accepts(value) {
+                    invalid(column.type_error("eq"));
+                }
+            }
+            LoanFilter::In { column, values } => {
+                if !values.iter().all(|value| column.accepts(value)) {
+                    invalid(column.type_error("in"));
+                }
+            }
+            LoanFilter::Range { column, min, max } => {
+                if min.is_none() && max.is_none() {
+                    invalid(format!("range on {} needs a min or max", column.column()));
+                } else if !min.iter().chain(max).all(|value| column.accepts(value)) {
+                    invalid(column.type_error("range"));
+                }
+            }
+            LoanFilter::Contains { column, .. } => {
+                if column.kind() != ColumnKind::Text {
+                    invalid(format!(
+                        "contains is only supported on text columns, not {}",
+                        column.column()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Loan columns that searches can filter and order by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoanColumn {
+    LoanId,
+    CustomerName,
+    PropertyAddress,
+    OriginationDate,
+    MaturityDate,
+    LoanAmount,
+    RemainingBalance,
+    InterestRate,
+    MonthlyPayment,
+    Status,
+    ProductName,
+    ProductType,
+    SecurityName,
+    ServicerName,
+    CurrentStatus,
+}
+
+impl LoanColumn {
+    pub const ALL: [LoanColumn; 15] = [
+        LoanColumn::LoanId,
+        LoanColumn::CustomerName,
+        LoanColumn::PropertyAddress,
+        LoanColumn::OriginationDate,
+        LoanColumn::MaturityDate,
+        LoanColumn::LoanAmount,
+        LoanColumn::RemainingBalance,
+        LoanColumn::InterestRate,
+        LoanColumn::MonthlyPayment,
+        LoanColumn::Status,
+        LoanColumn::ProductName,
+        LoanColumn::ProductType,
+        LoanColumn::SecurityName,
+        LoanColumn::ServicerName,
+        LoanColumn::CurrentStatus,
+    ];
+
+    pub fn column(self) -> &'static str {
+        match self {
+            LoanColumn::LoanId => "loan_id",
+            LoanColumn::CustomerName => "customer_name",
+            LoanColumn::PropertyAddress => "property_address",
+            LoanColumn::OriginationDate => "origination_date",
+            LoanColumn::MaturityDate => "maturity_date",
+            LoanColumn::LoanAmount => "loan_amount",
+            LoanColumn::RemainingBalance => "remaining_balance",
+            LoanColumn::InterestRate => "interest_rate",
+            LoanColumn::MonthlyPayment => "monthly_payment",
+            LoanColumn::Status => "status",
+            LoanColumn::ProductName => "product_name",
+            LoanColumn::ProductType => "product_type",
+            LoanColumn::SecurityName => "security_name",
+            LoanColumn::ServicerName => "servicer_name",
+            LoanColumn::CurrentStatus => "current_status",
+        }
+    }
+
+    pub fn from_column(name: &str) -> Option<LoanColumn> {
+        LoanColumn::ALL.into_iter().find(|column| column.column() == name)
+    }
+
+    pub fn kind(self) -> ColumnKind {
+        match self {
+            LoanColumn::OriginationDate | LoanColumn::MaturityDate => ColumnKind::Date,
+            LoanColumn::LoanAmount
+            | LoanColumn::RemainingBalance
+            | LoanColumn::InterestRate
+            | LoanColumn::MonthlyPayment => ColumnKind::Number,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    /// The values a column backed by an enum may hold.
+    pub fn allowed_values(self) -> Option<Vec<&'static str>> {
+        match self {
+            LoanColumn::Status => Some(StatusCategory::ALL.map(StatusCategory::as_str).to_vec()),
+            LoanColumn::ProductType => Some(ProductType::ALL.map(ProductType::as_str).to_vec()),
+            LoanColumn::CurrentStatus => Some(LoanStatus::ALL.map(LoanStatus::as_str).to_vec()),
+            _ => None,
+        }
+    }
+
+    /// Whether a JSON filter or cursor value can be compared with this column.
+    pub fn accepts(self, value: &serde_json::Value) -> bool {
+        match self.kind() {
+            ColumnKind::Text => match (value.as_str(), self.allowed_values()) {
+                (Some(text), Some(allowed)) => allowed.contains(&text),
+                (text, None) => text.is_some(),
+                (None, _) => false,
+            },
+            ColumnKind::Number => money::from_json(value).is_some(),
+            ColumnKind::Date => {
+                value.as_str().is_some_and(|date| date.parse::<NaiveDate>().is_ok())
+            }
+        }
+    }
+
+    fn type_error(self, operator: &str) -> String {
+        if let Some(allowed) = self.allowed_values() {
+            return format!(
+                "{} on {} expects one of: {}",
+                operator,
+                self.column(),
+                allowed.join(", ")
+            );
+        }
+        let expected = match self.kind() {
+            ColumnKind::Text => "strings",
+            ColumnKind::Number => "numbers or numeric strings",
+            ColumnKind::Date => "YYYY-MM-DD dates",
+        };
+        format!("{} on {} expects {}", operator, self.column(), expected)
+    }
+
+    /// The loan's value in this column, as stored in a search cursor.
+    pub fn value(self, loan: &Loan) -> serde_json::Value {
+        match self {
+            LoanColumn::LoanId => loan.loan_id.clone().into(),
+            LoanColumn::CustomerName => loan.customer_name.clone().into(),
+            LoanColumn::PropertyAddress => loan.property_address.clone().into(),
+            LoanColumn::OriginationDate => loan.origination_date.to_string().into(),
+            LoanColumn::MaturityDate => loan.maturity_date.to_string().into(),
+            LoanColumn::LoanAmount => loan.loan_amount.to_string().into(),
+            LoanColumn::RemainingBalance => loan.remaining_balance.to_string().into(),
+            LoanColumn::InterestRate => loan.interest_rate.to_string().into(),
+            LoanColumn::MonthlyPayment => loan.monthly_payment.to_string().into(),
+            LoanColumn::Status => loan.status.as_str().into(),
+            LoanColumn::ProductName => loan.product_name.clone().into(),
+            LoanColumn::ProductType => loan.product_type.as_str().into(),
+            LoanColumn::SecurityName => loan.security_name.clone().into(),
+            LoanColumn::ServicerName => loan.servicer_name.clone().into(),
+            LoanColumn::CurrentStatus => loan.current_status.as_str().into(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Number,
+    Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: LoanColumn,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Canonical `sort` parameter for the keys, used to tie a cursor to its ordering.
+    pub fn spec(keys: &[SortKey]) -> String {
+        keys.iter()
+            .map(|key| format!("{}{}", if key.descending { "-" } else { "" }, key.column.column()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Keyset position in a search, handed to clients as an opaque base64 token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchCursor {
+    /// Canonical sort the cursor was issued for.
+    pub sort: String,
+    /// Sort column values of the row the page starts after (or before, when `backward`).
+    pub values: Vec<serde_json::Value>,
+    pub backward: bool,
+}
+
+impl SearchCursor {
+    pub fn at(loan: &Loan, keys: &[SortKey], backward: bool) -> SearchCursor {
+        SearchCursor {
+            sort: SortKey::spec(keys),
+            values: keys.iter().map(|key| key.column.value(loan)).collect(),
+            backward,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Option<SearchCursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Whether the cursor holds one value per sort key, each readable as its column's type,
+    /// so that a forged cursor is rejected before it reaches the query.
+    pub fn fits(&self, keys: &[SortKey]) -> bool {
+        self.values.len() == keys.len()
+            && keys
+                .iter()
+                .zip(&self.values)
+                .all(|(key, value)| key.column.accepts(value))
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct SearchResponse {
-    pub data: Vec<Loan>,
+    pub data: Vec<SearchHit>,
     pub total: i64,
     pub page: i32,
     pub page_size: i32,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    /// Counts across every loan matching the filters, not just the current page.
+    pub facets: SearchFacets,
+}
+
+/// A search result: the loan plus the relevance details of `q` and `similar` searches.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub loan: Loan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f32>,
+    /// HTML-escaped text around the matches, with matched terms wrapped in `<mark>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headline: Option<String>,
+    /// Best word similarity of customer name or property address, for `similar` searches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFacets {
+    pub status: Vec<FacetCount>,
+    pub product_type: Vec<FacetCount>,
+    pub servicer_name: Vec<FacetCount>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanPayment {
+    pub payment_id: Uuid,
+    pub loan_id: String,
+    pub payment_date: NaiveDate,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub interest_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub principal_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub escrow_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub fees_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub balance_after: Decimal,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// A payment to post against a loan. The escrow and fee portions are taken out
+/// first; the rest goes to accrued interest and then to principal.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PostPaymentRequest {
+    pub payment_date: NaiveDate,
+    pub amount: Decimal,
+    #[serde(default)]
+    pub escrow_amount: Decimal,
+    #[serde(default)]
+    pub fees_amount: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AmortizationSchedule {
+    pub loan_id: String,
+    pub number_of_payments: u32,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub computed_monthly_payment: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub stored_monthly_payment: Decimal,
+    /// True when the stored `monthly_payment` differs from the level payment by more than a cent.
+    pub payment_mismatch: bool,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub total_interest: Decimal,
+    pub entries: Vec<ScheduleEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleEntry {
+    pub period: u32,
+    pub payment_date: NaiveDate,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub payment: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub interest: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub principal: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub ending_balance: Decimal,
+}
+
+/// Grouping for `/api/v1/loans/statistics`; the loans are selected with [`SearchFilters`].
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatisticsParams {
+    /// Comma-separated dimensions, outermost first, e.g. `status,servicer`.
+    pub group_by: Option<String>,
+}
+
+impl StatisticsParams {
+    pub const MAX_DIMENSIONS: usize = 3;
+
+    pub fn dimensions(&self) -> Result<Vec<StatisticsDimension>, FieldError> {
+        let mut dimensions = Vec::new();
+
+        for name in self.group_by.as_deref().unwrap_or("").split(',').map(str::trim) {
+            if name.is_empty() {
+                continue;
+            }
+            let dimension = StatisticsDimension::from_name(name).ok_or_else(|| FieldError {
+                field: "group_by",
+                message: format!(
+                    "unknown dimension {}; allowed: {}",
+                    name,
+                    StatisticsDimension::ALL.map(StatisticsDimension::name).join(", ")
+                ),
+            })?;
+            if dimensions.contains(&dimension) {
+                return Err(FieldError {
+                    field: "group_by",
+                    message: format!("dimension {} is listed more than once", name),
+                });
+            }
+            dimensions.push(dimension);
+        }
+
+        if dimensions.len() > Self::MAX_DIMENSIONS {
+            return Err(FieldError {
+                field: "group_by",
+                message: format!("at most {} dimensions are supported", Self::MAX_DIMENSIONS),
+            });
+        }
+
+        Ok(dimensions)
+    }
+}
+
+/// Attributes that pool statistics can be broken down by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatisticsDimension {
+    Status,
+    CurrentStatus,
+    ProductType,
+    Servicer,
+    SecurityName,
+    /// Two-letter state parsed from the end of `property_address`.
+    State,
+}
+
+impl StatisticsDimension {
+    pub const ALL: [StatisticsDimension; 6] = [
+        StatisticsDimension::Status,
+        StatisticsDimension::CurrentStatus,
+        StatisticsDimension::ProductType,
+        StatisticsDimension::Servicer,
+        StatisticsDimension::SecurityName,
+        StatisticsDimension::State,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StatisticsDimension::Status => "status",
+            StatisticsDimension::CurrentStatus => "current_status",
+            StatisticsDimension::ProductType => "product_type",
+            StatisticsDimension::Servicer => "servicer",
+            StatisticsDimension::SecurityName => "security_name",
+            StatisticsDimension::State => "state",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StatisticsDimension> {
+        match name {
+            "servicer_name" => Some(StatisticsDimension::Servicer),
+            _ => StatisticsDimension::ALL.into_iter().find(|dimension| dimension.name() == name),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Statistics {
-    pub total_loans: i64,
-    pub total_loan_amount: f64,
-    pub total_remaining_balance: f64,
-    pub average_interest_rate: f64,
+    #[serde(flatten)]
+    pub metrics: PoolMetrics,
     pub status_breakdown: Vec<StatusCount>,
     pub product_type_breakdown: Vec<ProductTypeCount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<StatisticsGroup>>,
+}
+
+/// Pool-level figures. Weighted averages are weighted by remaining balance, so paid-off
+/// loans do not count towards them; maturity and age are in whole months as of today.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct PoolMetrics {
+    pub total_loans: i64,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub total_loan_amount: Decimal,
+    #[serde(serialize_with = "money::serialize_money")]
+    pub total_remaining_balance: Decimal,
+    /// Unweighted mean rate, kept for existing clients; prefer `weighted_average_coupon`.
+    pub average_interest_rate: Decimal,
+    pub weighted_average_coupon: Decimal,
+    pub weighted_average_maturity: Decimal,
+    pub weighted_average_loan_age: Decimal,
+    /// Remaining balance as a fraction of original loan amount.
+    pub pool_factor: Decimal,
+}
+
+impl PoolMetrics {
+    /// Applies the reporting scales of [`money`] to the exact aggregates.
+    pub fn rounded(self) -> PoolMetrics {
+        PoolMetrics {
+            total_loans: self.total_loans,
+            total_loan_amount: money::round_money(self.total_loan_amount),
+            total_remaining_balance: money::round_money(self.total_remaining_balance),
+            average_interest_rate: money::round_average(self.average_interest_rate),
+            weighted_average_coupon: money::round_average(self.weighted_average_coupon),
+            weighted_average_maturity: money::round_average(self.weighted_average_maturity),
+            weighted_average_loan_age: money::round_average(self.weighted_average_loan_age),
+            pool_factor: money::round_ratio(self.pool_factor),
+        }
+    }
+}
+
+/// One group of a breakdown, with the next `group_by` dimension nested inside it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatisticsGroup {
+    pub dimension: &'static str,
+    pub value: String,
+    #[serde(flatten)]
+    pub metrics: PoolMetrics,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[schema(no_recursion)]
+    pub groups: Vec<StatisticsGroup>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct StatusCount {
     pub status: String,
     pub count: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ProductTypeCount {
     pub product_type: String,
     pub count: i64,
 }
+
+/// Date range and bucket size for the `/api/v1/loans/trends/*` endpoints; the loans are
+/// selected with [`SearchFilters`].
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrendParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    #[param(inline)]
+    pub interval: TrendInterval,
+}
+
+impl TrendParams {
+    pub const MAX_BUCKETS: usize = 600;
+
+    /// Trends of past activity default to the 24 months up to today.
+    fn history_range(&self, today: NaiveDate) -> Result<TrendRange, FieldError> {
+        let from = TrendInterval::Month.start_of(today) - Months::new(23);
+        self.range(from, today)
+    }
+
+    /// Projections default to the 30 years from today.
+    fn forecast_range(&self, today: NaiveDate) -> Result<TrendRange, FieldError> {
+        self.range(today, today + Months::new(30 * 12) - Days::new(1))
+    }
+
+    fn range(
+        &self,
+        default_from: NaiveDate,
+        default_to: NaiveDate,
+    ) -> Result<TrendRange, FieldError> {
+        let from = self.from.unwrap_or(default_from);
+        let to = self.to.unwrap_or(default_to);
+        if from > to {
+            return Err(FieldError {
+                field: "from",
+                message: "must not be after to".to_string(),
+            });
+        }
+
+        let mut buckets = Vec::new();
+        let mut start = self.interval.start_of(from);
+        while start <= to {
+            if buckets.len() == Self::MAX_BUCKETS {
+                return Err(FieldError {
+                    field: "to",
+                    message: format!(
+                        "range covers more than {} {} buckets",
+                        Self::MAX_BUCKETS,
+                        self.interval.name()
+                    ),
+                });
+            }
+            buckets.push(start);
+            start = start + Months::new(self.interval.months());
+        }
+
+        Ok(TrendRange {
+            from,
+            to,
+            interval: self.interval,
+            buckets,
+        })
+    }
+}
+
+/// Series served under `/api/v1/loans/trends/{metric}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendMetric {
+    /// Count and amount of loans by `origination_date`.
+    Originations,
+    /// Scheduled balance and principal runoff through `maturity_date`.
+    Runoff,
+    /// Delinquency rate from the status history.
+    Delinquency,
+}
+
+impl TrendMetric {
+    /// Runoff is a projection, so its range defaults forward from today.
+    pub fn range(self, params: &TrendParams, today: NaiveDate) -> Result<TrendRange, FieldError> {
+        match self {
+            TrendMetric::Runoff => params.forecast_range(today),
+            TrendMetric::Originations | TrendMetric::Delinquency => params.history_range(today),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendInterval {
+    #[default]
+    Month,
+    Quarter,
+}
+
+impl TrendInterval {
+    pub fn name(self) -> &'static str {
+        match self {
+            TrendInterval::Month => "month",
+            TrendInterval::Quarter => "quarter",
+        }
+    }
+
+    pub fn months(self) -> u32 {
+        match self {
+            TrendInterval::Month => 1,
+            TrendInterval::Quarter => 3,
+        }
+    }
+
+    /// First day of the bucket containing `date`.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        let month0 = date.month0() - date.month0() % self.months();
+        NaiveDate::from_ymd_opt(date.year(), month0 + 1, 1).unwrap_or(date)
+    }
+
+    /// Axis label for the bucket starting on `start`, e.g. `2024-03` or `2024-Q1`.
+    pub fn label(self, start: NaiveDate) -> String {
+        match self {
+            TrendInterval::Month => start.format("%Y-%m").to_string(),
+            TrendInterval::Quarter => format!("{}-Q{}", start.year(), start.month0() / 3 + 1),
+        }
+    }
+}
+
+/// Validated [`TrendParams`]: every bucket start from the one containing `from` up to `to`.
+#[derive(Debug, Clone)]
+pub struct TrendRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub interval: TrendInterval,
+    pub buckets: Vec<NaiveDate>,
+}
+
+/// A chart-ready time series: `labels`, `periods` and every series' `data` line up by index.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Trend {
+    pub interval: TrendInterval,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub labels: Vec<String>,
+    /// First day of each bucket.
+    pub periods: Vec<NaiveDate>,
+    pub series: Vec<TrendSeries>,
+}
+
+impl Trend {
+    pub fn new(range: &TrendRange, series: Vec<TrendSeries>) -> Trend {
+        Trend {
+            interval: range.interval,
+            from: range.from,
+            to: range.to,
+            labels: range.buckets.iter().map(|&start| range.interval.label(start)).collect(),
+            periods: range.buckets.clone(),
+            series,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrendSeries {
+    pub name: &'static str,
+    pub data: Vec<Decimal>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(sort: &str, cursor: Option<String>) -> LoanQuery {
+        LoanQuery {
+            filter: None,
+            q: None,
+            similar: None,
+            min_similarity: None,
+            sort: Some(sort.to_string()),
+            page: None,
+            page_size: None,
+            cursor,
+        }
+    }
+
+    fn cursor(sort: &str, values: Vec<serde_json::Value>) -> Option<String> {
+        Some(SearchCursor { sort: sort.to_string(), values, backward: false }.encode())
+    }
+
+    fn error_fields(errors: Vec<FieldError>) -> Vec<&'static str> {
+        errors.iter().map(|error| error.field).collect()
+    }
+
+    const CSV_HEADER: &str = "loanId,customerName,propertyAddress,originationDate,maturityDate,\
+        loanAmount,remainingBalance,interestRate,monthlyPayment,status,productName,productType,\
+        securityName,servicerName,currentStatus";
+
+    fn csv_row(loan_id: &str, loan_amount: &str) -> String {
+        format!(
+            "{},Jane Doe,\"1 Main St, Springfield, IL\",2024-01-15,2054-01-15,{},200000.00,\
+             6.000,1199.10,Active,30 Year Fixed,Fixed Rate,POOL-1,Acme Servicing,Current",
+            loan_id, loan_amount
+        )
+    }
+
+    fn ndjson_row(loan_id: &str, loan_amount: &str) -> String {
+        json!({
+            "loanId": loan_id,
+            "customerName": "Jane Doe",
+            "propertyAddress": "1 Main St, Springfield, IL",
+            "originationDate": "2024-01-15",
+            "maturityDate": "2054-01-15",
+            "loanAmount": loan_amount,
+            "remainingBalance": "200000.00",
+            "interestRate": "6.000",
+            "monthlyPayment": "1199.10",
+            "status": "Active",
+            "productName": "30 Year Fixed",
+            "productType": "Fixed Rate",
+            "securityName": "POOL-1",
+            "servicerName": "Acme Servicing",
+            "currentStatus": "Current",
+        })
+        .to_string()
+    }
+
+    /// Each row as its line and either the loan ID or the error message.
+    fn outcomes(rows: Vec<ImportRow>) -> Vec<(u64, Result<String, String>)> {
+        rows.into_iter()
+            .map(|row| {
+                let loan = row.loan.map(|loan| loan.loan_id).map_err(|error| error.message);
+                (row.line, loan)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn csv_rows_parse_with_their_lines() {
+        let rows = [csv_row("LN-1", "200000.00"), csv_row("LN-2", "1e5")];
+        let file = format!("{}\n{}\n{}\n", CSV_HEADER, rows[0], rows[1]);
+        let rows = ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Csv);
+        assert_eq!(outcomes(rows), [(2, Ok("LN-1".to_string())), (3, Ok("LN-2".to_string()))]);
+    }
+
+    #[test]
+    fn csv_header_mismatch_fails_every_row() {
+        let header = CSV_HEADER.replace("interestRate", "rate");
+        let rows = [csv_row("LN-1", "1.00"), csv_row("LN-2", "2.00")];
+        let file = format!("{}\n{}\n{}\n", header, rows[0], rows[1]);
+        let rows = outcomes(ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Csv));
+        assert_eq!(rows.len(), 2);
+        for (_, loan) in rows {
+            assert!(loan.unwrap_err().contains("missing field `interestRate`"));
+        }
+    }
+
+    #[test]
+    fn bad_decimals_are_reported_on_their_row() {
+        let rows = [csv_row("LN-1", "12.3.4"), csv_row("LN-2", "1")];
+        let file = format!("{}\n{}\n{}\n", CSV_HEADER, rows[0], rows[1]);
+        let rows = outcomes(ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Csv));
+        assert_eq!(rows[0].0, 2);
+        assert!(rows[0].1.as_ref().unwrap_err().contains("12.3.4"), "{:?}", rows[0]);
+        assert_eq!(rows[1], (3, Ok("LN-2".to_string())));
+
+        let file = format!("{}\n{}\n", ndjson_row("LN-1", "abc"), ndjson_row("LN-2", "1.00"));
+        let rows = outcomes(ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Ndjson));
+        assert_eq!(rows[0].0, 1);
+        assert!(rows[0].1.as_ref().unwrap_err().contains("abc"), "{:?}", rows[0]);
+        assert_eq!(rows[1], (2, Ok("LN-2".to_string())));
+    }
+
+    #[test]
+    fn empty_files_have_no_rows() {
+        for format in [LoanFileFormat::Csv, LoanFileFormat::Ndjson] {
+            assert!(ImportRow::parse_all(b"", format).is_empty());
+            assert!(ImportRow::parse_all(b"\n  \n", format).is_empty());
+        }
+        let header_only = format!("{}\n", CSV_HEADER);
+        assert!(ImportRow::parse_all(header_only.as_bytes(), LoanFileFormat::Csv).is_empty());
+    }
+
+    #[test]
+    fn errors_report_the_line_each_row_starts_on() {
+        // CRLF endings and an address quoted across two lines.
+        let multi_line = csv_row("LN-2", "1.00").replace("1 Main St, ", "1 Main St\r\n");
+        let file = format!(
+            "{}\r\n{}\r\n{}\r\nnot,enough,columns\r\n{}\r\n",
+            CSV_HEADER,
+            csv_row("LN-1", "1.00"),
+            multi_line,
+            csv_row("LN-4", "1.00")
+        );
+        let rows = outcomes(ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Csv));
+        assert_eq!(rows[0], (2, Ok("LN-1".to_string())));
+        assert_eq!(rows[1], (3, Ok("LN-2".to_string())));
+        assert_eq!(rows[2].0, 5);
+        assert!(rows[2].1.is_err());
+        assert_eq!(rows[3], (6, Ok("LN-4".to_string())));
+
+        // Blank lines are skipped but still counted.
+        let rows = [ndjson_row("LN-1", "1.00"), ndjson_row("LN-4", "1.00")];
+        let file = format!("{}\n\n{{\"loanId\": \n{}\n", rows[0], rows[1]);
+        let rows = outcomes(ImportRow::parse_all(file.as_bytes(), LoanFileFormat::Ndjson));
+        assert_eq!(rows[0], (1, Ok("LN-1".to_string())));
+        assert_eq!(rows[1].0, 3);
+        assert!(rows[1].1.is_err());
+        assert_eq!(rows[2], (4, Ok("LN-4".to_string())));
+    }
+
+    #[test]
+    fn status_lifecycle() {
+        use LoanStatus::*;
+
+        let allowed = [
+            (Current, ThirtyDaysLate),
+            (Current, PaidOff),
+            (ThirtyDaysLate, Current),
+            (ThirtyDaysLate, SixtyDaysLate),
+            (ThirtyDaysLate, PaidOff),
+            (SixtyDaysLate, Current),
+            (SixtyDaysLate, ThirtyDaysLate),
+            (SixtyDaysLate, NinetyDaysLate),
+            (SixtyDaysLate, PaidOff),
+            (NinetyDaysLate, Current),
+            (NinetyDaysLate, ThirtyDaysLate),
+            (NinetyDaysLate, SixtyDaysLate),
+            (NinetyDaysLate, Default),
+            (NinetyDaysLate, PaidOff),
+            (Default, Current),
+            (Default, InForeclosure),
+            (Default, PaidOff),
+            (Default, ChargedOff),
+            (InForeclosure, Current),
+            (InForeclosure, PaidOff),
+            (InForeclosure, ChargedOff),
+        ];
+
+        for from in LoanStatus::ALL {
+            for to in LoanStatus::ALL {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{} -> {}",
+                    from.as_str(),
+                    to.as_str()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn late_loans_cannot_skip_stages_and_closed_loans_stay_closed() {
+        use LoanStatus::*;
+
+        for (from, to) in [
+            (Current, SixtyDaysLate),
+            (Current, Default),
+            (ThirtyDaysLate, NinetyDaysLate),
+            (Current, ChargedOff),
+            (PaidOff, Current),
+            (PaidOff, ThirtyDaysLate),
+            (ChargedOff, Current),
+            (ChargedOff, PaidOff),
+            (Current, Current),
+        ] {
+            assert!(!from.can_transition_to(to), "{} -> {}", from.as_str(), to.as_str());
+        }
+    }
+
+    #[test]
+    fn cursor_issued_for_the_sort_is_accepted() {
+        let token = cursor("-loan_amount,loan_id", vec![json!("250000.00"), json!("LN-1")]);
+        assert!(query("-loan_amount", token).validate().is_empty());
+    }
+
+    #[test]
+    fn cursor_for_another_sort_is_rejected() {
+        let token = cursor("loan_id", vec![json!("LN-1")]);
+        assert_eq!(error_fields(query("-loan_amount", token).validate()), ["cursor"]);
+    }
+
+    #[test]
+    fn forged_cursor_values_are_rejected() {
+        let token = cursor("-loan_amount,loan_id", vec![json!("abc"), json!("x")]);
+        assert_eq!(error_fields(query("-loan_amount", token).validate()), ["cursor"]);
+
+        let token = cursor("origination_date,loan_id", vec![json!("2024-13-01"), json!("x")]);
+        assert_eq!(error_fields(query("origination_date", token).validate()), ["cursor"]);
+
+        let token = cursor("status,loan_id", vec![json!(1), json!("x")]);
+        assert_eq!(error_fields(query("status", token).validate()), ["cursor"]);
+    }
+
+    #[test]
+    fn cursor_with_missing_or_extra_values_is_rejected() {
+        let token = cursor("-loan_amount,loan_id", vec![json!("250000.00")]);
+        assert_eq!(error_fields(query("-loan_amount", token).validate()), ["cursor"]);
+
+        let token = cursor("-loan_amount,loan_id", vec![json!("1"), json!("LN-1"), json!("LN-2")]);
+        assert_eq!(error_fields(query("-loan_amount", token).validate()), ["cursor"]);
+    }
+
+    #[test]
+    fn undecodable_cursor_is_rejected() {
+        let token = Some("not a cursor".to_string());
+        assert_eq!(error_fields(query("loan_id", token).validate()), ["cursor"]);
+    }
+}
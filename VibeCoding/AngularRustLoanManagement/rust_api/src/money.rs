@@ -0,0 +1,167 @@
+// src/money.rs
+//! Rounding rules for money and rates. Amounts and rates are exact decimals throughout;
+//! results are only rounded where they are stored or reported, always half away from
+//! zero, to the scale listed here:
+//!
+//! | Value                                  | Scale |
+//! |----------------------------------------|-------|
+//! | Money (balances, payments, interest)   | 2     |
+//! | Interest rates, in percent             | 3     |
+//! | Averages (WAC, WAM, WALA, mean rate)   | 4     |
+//! | Ratios (pool factor, delinquency rate) | 8     |
+//!
+//! Money and rate scales match the `DECIMAL(15,2)` and `DECIMAL(5,3)` columns, so input
+//! with more places is rejected rather than rounded. Numbers in JSON bodies and CSV files
+//! are read through their shortest `f64` form, which is exact for the 15 significant
+//! digits those columns hold; values are serialized as strings, money and rates at their
+//! scale even where the value itself has fewer places, such as a defaulted zero.
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Serialize, Serializer};
+use std::str::FromStr;
+
+pub const MONEY_SCALE: u32 = 2;
+pub const RATE_SCALE: u32 = 3;
+pub const AVERAGE_SCALE: u32 = 4;
+pub const RATIO_SCALE: u32 = 8;
+
+const STRATEGY: RoundingStrategy = RoundingStrategy::MidpointAwayFromZero;
+
+pub fn round_money(value: Decimal) -> Decimal {
+    round(value, MONEY_SCALE)
+}
+
+pub fn round_average(value: Decimal) -> Decimal {
+    round(value, AVERAGE_SCALE)
+}
+
+pub fn round_ratio(value: Decimal) -> Decimal {
+    round(value, RATIO_SCALE)
+}
+
+/// Rounds to exactly `scale` places, padding with zeros so `0` serializes as `0.00`.
+fn round(value: Decimal, scale: u32) -> Decimal {
+    let mut rounded = value.round_dp_with_strategy(scale, STRATEGY);
+    rounded.rescale(scale);
+    rounded
+}
+
+/// Serializes an amount at [`MONEY_SCALE`], for `#[serde(serialize_with)]`.
+pub fn serialize_money<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    Serialize::serialize(&round_money(*value), serializer)
+}
+
+/// Serializes a rate at [`RATE_SCALE`], for `#[serde(serialize_with)]`.
+pub fn serialize_rate<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    Serialize::serialize(&round(*value, RATE_SCALE), serializer)
+}
+
+/// Whether `value` fits in `scale` decimal places without rounding.
+pub fn has_scale(value: Decimal, scale: u32) -> bool {
+    value.normalize().scale() <= scale
+}
+
+/// Monthly rate as a fraction from an annual percentage rate, e.g. 6 -> 0.005.
+pub fn monthly_rate(annual_percent: Decimal) -> Decimal {
+    annual_percent / Decimal::from(1200)
+}
+
+/// Reads a JSON number or numeric string, as sent in filters and stored in cursors.
+pub fn from_json(value: &serde_json::Value) -> Option<Decimal> {
+    let text = match value {
+        serde_json::Value::Number(number) => number.to_string(),
+        serde_json::Value::String(text) => text.clone(),
+        _ => return None,
+    };
+    Decimal::from_str(&text).or_else(|_| Decimal::from_scientific(&text)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn money_rounds_half_away_from_zero() {
+        assert_eq!(round_money(dec("2.345")), dec("2.35"));
+        assert_eq!(round_money(dec("2.344999")), dec("2.34"));
+        assert_eq!(round_money(dec("-2.345")), dec("-2.35"));
+        assert_eq!(round_money(dec("0.005")), dec("0.01"));
+    }
+
+    #[test]
+    fn rounding_pads_to_the_scale() {
+        assert_eq!(round_money(Decimal::ZERO).to_string(), "0.00");
+        assert_eq!(round_money(dec("12.5")).to_string(), "12.50");
+        assert_eq!(round_ratio(Decimal::ONE).to_string(), "1.00000000");
+    }
+
+    #[test]
+    fn averages_and_ratios_keep_their_scale() {
+        assert_eq!(round_average(dec("3.12345")), dec("3.1235"));
+        assert_eq!(round_ratio(dec("0.123456785")), dec("0.12345679"));
+        assert_eq!(round_ratio(Decimal::ONE / Decimal::from(3)), dec("0.33333333"));
+    }
+
+    #[test]
+    fn sums_are_exact() {
+        let total: Decimal = (0..10).map(|_| dec("0.10")).sum();
+        assert_eq!(total, Decimal::ONE);
+    }
+
+    #[test]
+    fn money_and_rates_serialize_at_their_scale() {
+        #[derive(Serialize)]
+        struct Amounts {
+            #[serde(serialize_with = "serialize_money")]
+            zero: Decimal,
+            #[serde(serialize_with = "serialize_money")]
+            whole: Decimal,
+            #[serde(serialize_with = "serialize_money")]
+            cents: Decimal,
+            #[serde(serialize_with = "serialize_rate")]
+            rate: Decimal,
+        }
+
+        let amounts = Amounts {
+            zero: Decimal::ZERO,
+            whole: dec("1500"),
+            cents: dec("1199.10"),
+            rate: dec("6"),
+        };
+        assert_eq!(
+            serde_json::to_value(amounts).unwrap(),
+            serde_json::json!({
+                "zero": "0.00",
+                "whole": "1500.00",
+                "cents": "1199.10",
+                "rate": "6.000",
+            })
+        );
+    }
+
+    #[test]
+    fn scale_ignores_trailing_zeros() {
+        assert!(has_scale(dec("100.10"), MONEY_SCALE));
+        assert!(has_scale(dec("100.1000"), MONEY_SCALE));
+        assert!(!has_scale(dec("100.101"), MONEY_SCALE));
+        assert!(has_scale(dec("4.125"), RATE_SCALE));
+    }
+
+    #[test]
+    fn monthly_interest_on_a_balance() {
+        let interest = round_money(dec("200000.00") * monthly_rate(dec("4.125")));
+        assert_eq!(interest, dec("687.50"));
+    }
+
+    #[test]
+    fn json_numbers_and_strings() {
+        assert_eq!(from_json(&serde_json::json!(1234.56)), Some(dec("1234.56")));
+        assert_eq!(from_json(&serde_json::json!("1234.56")), Some(dec("1234.56")));
+        assert_eq!(from_json(&serde_json::json!(1e3)), Some(dec("1000")));
+        assert_eq!(from_json(&serde_json::json!("abc")), None);
+        assert_eq!(from_json(&serde_json::json!(true)), None);
+    }
+}
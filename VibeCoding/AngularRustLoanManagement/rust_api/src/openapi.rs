@@ -0,0 +1,121 @@
+// src/openapi.rs
+//! The OpenAPI document served at `/api-docs/openapi.json`, generated from the handler
+//! annotations and the model types so that clients can be generated from it.
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Content, Ref, RefOr, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers::loan_handlers;
+use crate::health;
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Responses any `/api/v1/loans` operation can return, keyed by status code.
+const COMMON_RESPONSES: [(&str, &str, &str); 4] = [
+    ("401", "Unauthorized", "Missing, invalid or expired bearer token"),
+    ("403", "Forbidden", "The caller's role does not allow this operation"),
+    ("500", "InternalError", "Unexpected server error"),
+    ("503", "Unavailable", "The database is unreachable or has no free connections"),
+];
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Loan Management API",
+        description = "Search, servicing and reporting for the loan portfolio."
+    ),
+    paths(
+        loan_handlers::search_loans,
+        loan_handlers::query_loans,
+        loan_handlers::export_loans,
+        loan_handlers::import_loans,
+        loan_handlers::get_loan_by_id,
+        loan_handlers::get_statistics,
+        loan_handlers::get_trend,
+        loan_handlers::create_loan,
+        loan_handlers::update_loan,
+        loan_handlers::patch_loan,
+        loan_handlers::delete_loan,
+        loan_handlers::post_payment,
+        loan_handlers::get_payments,
+        loan_handlers::get_schedule,
+        loan_handlers::transition_status,
+        loan_handlers::get_status_history,
+        loan_handlers::get_audit_trail,
+        health::live,
+        health::ready,
+    ),
+    tags(
+        (name = "loans", description = "Loan search, servicing and reporting"),
+        (name = "health", description = "Probes for monitoring; no token needed")
+    ),
+    modifiers(&BearerAuth, &ProblemResponses),
+    security(("bearer" = []))
+)]
+pub struct ApiDoc;
+
+pub fn document() -> utoipa::openapi::OpenApi {
+    let mut document = ApiDoc::openapi();
+    // Cargo.toml declares no license, which would be rendered as one with an empty name.
+    document.info.license = None;
+    document
+}
+
+/// Declares the JWT bearer scheme every operation is secured with.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        let scheme = HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT");
+        components
+            .security_schemes
+            .insert("bearer".to_string(), SecurityScheme::Http(scheme.build()));
+    }
+}
+
+/// Error bodies of the loan API are `application/problem+json` rather than the
+/// `application/json` the annotations default to, and every loan operation can fail with
+/// the [`COMMON_RESPONSES`]. The monitoring endpoints answer with their own bodies.
+struct ProblemResponses;
+
+impl Modify for ProblemResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let problem = || Content::new(Some(Ref::from_schema_name("Problem")));
+        let components = openapi.components.get_or_insert_with(Default::default);
+        for (_, name, description) in COMMON_RESPONSES {
+            let response = ResponseBuilder::new()
+                .description(description)
+                .content(PROBLEM_JSON, problem())
+                .build();
+            components.responses.insert(name.to_string(), RefOr::T(response));
+        }
+
+        let paths = openapi.paths.paths.iter_mut();
+        for (_, item) in paths.filter(|(path, _)| path.starts_with("/api/")) {
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.patch,
+            ];
+            for operation in operations.into_iter().flatten() {
+                let responses = &mut operation.responses.responses;
+                for (status, response) in responses.iter_mut() {
+                    let RefOr::T(response) = response else { continue };
+                    if status.starts_with('4') || status.starts_with('5') {
+                        if let Some(content) = response.content.shift_remove("application/json") {
+                            response.content.insert(PROBLEM_JSON.to_string(), content);
+                        }
+                    }
+                }
+                for (status, name, _) in COMMON_RESPONSES {
+                    responses
+                        .entry(status.to_string())
+                        .or_insert_with(|| RefOr::Ref(Ref::from_response_name(name)));
+                }
+            }
+        }
+    }
+}
@@ -1,190 +1,2178 @@
 // src/services.rs
-use crate::models::{Loan, SearchFilters, SearchResponse, Statistics, StatusCount, ProductTypeCount};
-use sqlx::PgPool;
-use anyhow::Result;
+use crate::models::{
+    AmortizationSchedule, AuditEntry, AuditFilters, ColumnKind, CreateLoanRequest, FacetCount,
+    FieldError, ImportReport, ImportRow, ImportRowError, Loan, LoanColumn, LoanFields, LoanFilter,
+    LoanPayment, LoanQuery, LoanStatus, LoanStatusChange, PatchLoanRequest, PoolMetrics,
+    PostPaymentRequest, ProductType, ProductTypeCount, ScheduleEntry, SearchCursor, SearchFacets,
+    SearchHit, SearchResponse, SortKey, Statistics, StatisticsDimension, StatisticsGroup,
+    StatusCategory, StatusCount, StatusTransitionRequest, Trend, TrendMetric, TrendRange,
+    TrendSeries, UpdateLoanRequest,
+};
+use crate::auth::{Principal, ServicerScope};
+use crate::metrics;
+use crate::money;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rust_decimal::{Decimal, MathematicalOps};
+use sqlx::{FromRow, PgPool, Postgres, Row, Transaction};
+use anyhow::{anyhow, bail, Context, Result};
+use async_stream::try_stream;
+use futures_util::{Stream, TryStreamExt};
+use std::collections::{HashMap, HashSet};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
 
 pub struct LoanService;
 
+/// Result of a loan write that is guarded by validation and the `updated_at` version.
+#[derive(Debug)]
+pub enum WriteOutcome<T> {
+    Applied(T),
+    NotFound,
+    /// The loan already exists or was modified since the client read it; carries the stored row.
+    Conflict(Option<Box<Loan>>),
+    Invalid(Vec<FieldError>),
+    /// Other records depend on the loan in its current state; carries the reason.
+    Blocked(&'static str),
+}
+
 impl LoanService {
-    pub async fn search_loans(pool: &PgPool, filters: SearchFilters) -> Result<SearchResponse> {
-        let page = filters.page.unwrap_or(1);
-        let page_size = filters.page_size.unwrap_or(50);
-        let offset = (page - 1) * page_size;
+    #[instrument(name = "LoanService::search_loans", skip_all)]
+    pub async fn search_loans(
+        pool: &PgPool,
+        query: LoanQuery,
+        scope: &ServicerScope,
+    ) -> Result<SearchResponse> {
+        let _timer = metrics::query_timer("search_loans");
+        let page = query.page.unwrap_or(1);
+        let page_size = query.page_size.unwrap_or(LoanQuery::DEFAULT_PAGE_SIZE);
+        let offset = (i64::from(page) - 1) * i64::from(page_size);
+        let cursor = match query.cursor.as_deref() {
+            Some(token) => Some(SearchCursor::decode(token).context("Invalid search cursor")?),
+            None => None,
+        };
+        let backward = cursor.as_ref().is_some_and(|cursor| cursor.backward);
+        let sort = query.sort_keys().map_err(|error| anyhow!("Invalid sort: {}", error.message))?;
+        let ranked = query.ranked();
 
         let mut query_builder = sqlx::QueryBuilder::new(
             "SELECT loan_id, customer_name, property_address, origination_date, 
              maturity_date, loan_amount, remaining_balance, interest_rate, 
              monthly_payment, status, product_name, product_type, 
-             security_name, servicer_name, current_status FROM loans WHERE 1=1"
+             security_name, servicer_name, current_status, updated_at, "
         );
 
-        // Add filters
-        if let Some(customer_name) = &filters.customer_name {
-            query_builder.push(" AND customer_name ILIKE ");
-            query_builder.push_bind(format!("%{}%", customer_name));
+        match &query.q {
+            Some(q) => {
+                query_builder.push("ts_rank(search_vector, websearch_to_tsquery('simple', ");
+                query_builder.push_bind(q.clone());
+                query_builder.push(")) AS rank, ts_headline('simple', ");
+                query_builder.push(HEADLINE_DOCUMENT);
+                query_builder.push(", websearch_to_tsquery('simple', ");
+                query_builder.push_bind(q.clone());
+                query_builder.push("), 'StartSel=<mark>, StopSel=</mark>') AS headline");
+            }
+            None => {
+                query_builder.push("NULL::real AS rank, NULL::text AS headline");
+            }
         }
 
-        if let Some(status) = &filters.status {
-            query_builder.push(" AND status = ");
-            query_builder.push_bind(status);
+        match &query.similar {
+            Some(similar) => {
+                query_builder.push(", GREATEST(word_similarity(");
+                query_builder.push_bind(similar.clone());
+                query_builder.push(", customer_name), word_similarity(");
+                query_builder.push_bind(similar.clone());
+                query_builder.push(", property_address)) AS score");
+            }
+            None => {
+                query_builder.push(", NULL::real AS score");
+            }
         }
 
-        if let Some(product_type) = &filters.product_type {
-            query_builder.push(" AND product_type = ");
-            query_builder.push_bind(product_type);
+        query_builder.push(" FROM loans WHERE 1=1");
+        push_conditions(&mut query_builder, &query, scope)?;
+
+        // Keyset pagination: continue from the cursor row instead of skipping with OFFSET.
+        if let Some(cursor) = &cursor {
+            push_keyset(&mut query_builder, &sort, cursor)?;
         }
 
-        if let Some(servicer_name) = &filters.servicer_name {
-            query_builder.push(" AND servicer_name ILIKE ");
-            query_builder.push_bind(format!("%{}%", servicer_name));
+        // Walking backwards reverses every key; the page is flipped back after fetching.
+        // One extra row tells us whether another page follows in the direction of travel.
+        query_builder.push(" ORDER BY ");
+        if ranked {
+            if query.q.is_some() {
+                query_builder.push("rank DESC, ");
+            }
+            if query.similar.is_some() {
+                query_builder.push("score DESC, ");
+            }
+            query_builder.push("loan_id");
+        } else {
+            push_sort(&mut query_builder, &sort, backward);
+        }
+        query_builder.push(" LIMIT ");
+        query_builder.push_bind(i64::from(page_size) + 1);
+        if cursor.is_none() {
+            query_builder.push(" OFFSET ");
+            query_builder.push_bind(offset);
         }
 
-        if let Some(min_amount) = filters.min_loan_amount {
-            query_builder.push(" AND loan_amount >= ");
-            query_builder.push_bind(min_amount);
+        let mut tx = pool.begin().await?;
+        set_similarity_threshold(&mut tx, &query).await?;
+
+        let mut loans = query_builder
+            .build_query_as::<SearchHit>()
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let has_more = loans.len() > page_size as usize;
+        loans.truncate(page_size as usize);
+        if backward {
+            loans.reverse();
         }
 
-        if let Some(max_amount) = filters.max_loan_amount {
-            query_builder.push(" AND loan_amount <= ");
-            query_builder.push_bind(max_amount);
+        let (has_next, has_prev) = match &cursor {
+            None => (has_more, page > 1),
+            Some(cursor) if cursor.backward => (true, has_more),
+            Some(_) => (has_more, true),
+        };
+        // Relevance and similarity are not stable keys, so ranked results are paged by number only.
+        let next_cursor = loans
+            .last()
+            .filter(|_| has_next && !ranked)
+            .map(|hit| SearchCursor::at(&hit.loan, &sort, false).encode());
+        let prev_cursor = loans
+            .first()
+            .filter(|_| has_prev && !ranked)
+            .map(|hit| SearchCursor::at(&hit.loan, &sort, true).encode());
+
+        // Total and facet counts in one pass: each grouping set yields the rows for one facet,
+        // and the empty set yields the overall total.
+        let mut facet_query = sqlx::QueryBuilder::new(
+            "SELECT GROUPING(status, product_type, servicer_name),
+             COALESCE(status, product_type, servicer_name), COUNT(*) FROM loans WHERE 1=1",
+        );
+
+        push_conditions(&mut facet_query, &query, scope)?;
+
+        facet_query.push(
+            " GROUP BY GROUPING SETS ((status), (product_type), (servicer_name), ())
+             ORDER BY 3 DESC, 2",
+        );
+
+        let rows: Vec<(i32, Option<String>, i64)> =
+            facet_query.build_query_as().fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+
+        let mut total = 0;
+        let mut facets = SearchFacets::default();
+        for (grouping, value, count) in rows {
+            let facet = match grouping {
+                0b011 => &mut facets.status,
+                0b101 => &mut facets.product_type,
+                0b110 => &mut facets.servicer_name,
+                _ => {
+                    total = count;
+                    continue;
+                }
+            };
+            facet.push(FacetCount { value: value.unwrap_or_default(), count });
+        }
+
+        Ok(SearchResponse {
+            data: loans,
+            total,
+            page,
+            page_size,
+            next_cursor,
+            prev_cursor,
+            facets,
+        })
+    }
+
+    /// Every loan matching the query in `sort` order, read through a database cursor as the
+    /// stream is polled so that large exports are never held in memory. Paging is ignored.
+    pub fn export_loans(
+        pool: PgPool,
+        query: LoanQuery,
+        scope: ServicerScope,
+    ) -> impl Stream<Item = Result<Loan>> + 'static {
+        let span = tracing::info_span!("LoanService::export_loans");
+        try_stream! {
+            let _timer = metrics::query_timer("export_loans");
+            let sort = query.sort_keys().map_err(|error| anyhow!("Invalid sort: {}", error.message))?;
+
+            let mut query_builder = sqlx::QueryBuilder::new(
+                "SELECT loan_id, customer_name, property_address, origination_date,
+                 maturity_date, loan_amount, remaining_balance, interest_rate,
+                 monthly_payment, status, product_name, product_type,
+                 security_name, servicer_name, current_status, updated_at FROM loans WHERE 1=1",
+            );
+            push_conditions(&mut query_builder, &query, &scope)?;
+            query_builder.push(" ORDER BY ");
+            push_sort(&mut query_builder, &sort, false);
+
+            let mut tx = pool.begin().instrument(span.clone()).await?;
+            set_similarity_threshold(&mut tx, &query).instrument(span.clone()).await?;
+
+            let mut rows = query_builder.build_query_as::<Loan>().fetch(&mut *tx);
+            while let Some(loan) = rows.try_next().instrument(span.clone()).await? {
+                yield loan;
+            }
         }
+    }
+
+    #[instrument(name = "LoanService::get_loan_by_id", skip_all, fields(%loan_id))]
+    pub async fn get_loan_by_id(
+        pool: &PgPool,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<Loan>> {
+        let _timer = metrics::query_timer("get_loan_by_id");
+        Self::fetch_loan(pool, loan_id, scope).await
+    }
 
-        if let Some(date_from) = filters.origination_date_from {
-            query_builder.push(" AND origination_date >= ");
-            query_builder.push_bind(date_from);
+    /// [`Self::get_loan_by_id`] for the other methods, which are timed themselves.
+    async fn fetch_loan(
+        pool: &PgPool,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<Loan>> {
+        let loan = sqlx::query_as!(
+            Loan,
+            r#"SELECT loan_id, customer_name, property_address, origination_date, 
+             maturity_date, loan_amount, remaining_balance, interest_rate, 
+             monthly_payment, status AS "status: StatusCategory", product_name,
+             product_type AS "product_type: ProductType", security_name, servicer_name,
+             current_status AS "current_status: LoanStatus", updated_at
+             FROM loans WHERE loan_id = $1 AND ($2::text IS NULL OR servicer_name = $2)"#,
+            loan_id,
+            scope.servicer_name()
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(loan)
+    }
+
+    #[instrument(name = "LoanService::create_loan", skip_all)]
+    pub async fn create_loan(
+        pool: &PgPool,
+        request: CreateLoanRequest,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<Loan>> {
+        let _timer = metrics::query_timer("create_loan");
+        let mut errors = request.fields.validate();
+        errors.extend(check_scope(&principal.scope, &request.fields));
+        errors.extend(check_loan_id(&request.loan_id));
+        if !errors.is_empty() {
+            return Ok(WriteOutcome::Invalid(errors));
         }
 
-        if let Some(date_to) = filters.origination_date_to {
-            query_builder.push(" AND origination_date <= ");
-            query_builder.push_bind(date_to);
+        let fields = request.fields;
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+        let created = sqlx::query_as!(
+            Loan,
+            r#"INSERT INTO loans (
+                loan_id, customer_name, property_address, origination_date, maturity_date,
+                loan_amount, remaining_balance, interest_rate, monthly_payment, status,
+                product_name, product_type, security_name, servicer_name, current_status
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+             ON CONFLICT (loan_id) DO NOTHING
+             RETURNING loan_id, customer_name, property_address, origination_date,
+             maturity_date, loan_amount, remaining_balance, interest_rate,
+             monthly_payment, status AS "status: StatusCategory", product_name,
+             product_type AS "product_type: ProductType", security_name, servicer_name,
+             current_status AS "current_status: LoanStatus", updated_at"#,
+            request.loan_id,
+            fields.customer_name,
+            fields.property_address,
+            fields.origination_date,
+            fields.maturity_date,
+            fields.loan_amount,
+            fields.remaining_balance,
+            fields.interest_rate,
+            fields.monthly_payment,
+            fields.status.as_str(),
+            fields.product_name,
+            fields.product_type.as_str(),
+            fields.security_name,
+            fields.servicer_name,
+            fields.current_status.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match created {
+            Some(loan) => {
+                let to = loan.current_status;
+                let changed_by = principal.user_id.as_str();
+                Self::record_status_change(&mut tx, &loan.loan_id, None, to, None, changed_by).await?;
+                tx.commit().await?;
+                Ok(WriteOutcome::Applied(loan))
+            }
+            None => {
+                drop(tx);
+                let existing = Self::fetch_loan(pool, &request.loan_id, &principal.scope).await?;
+                Ok(WriteOutcome::Conflict(existing.map(Box::new)))
+            }
         }
+    }
 
-        query_builder.push(" ORDER BY loan_id LIMIT ");
-        query_builder.push_bind(page_size);
-        query_builder.push(" OFFSET ");
-        query_builder.push_bind(offset);
+    /// Validates every row of a loan tape and, unless this is a dry run or any row is invalid,
+    /// inserts or replaces all of them in one transaction. Existing loans must be visible to the
+    /// caller and may only move along the status lifecycle; `updatedAt` is not checked.
+    #[instrument(name = "LoanService::import_loans", skip_all, fields(rows = rows.len()))]
+    pub async fn import_loans(
+        pool: &PgPool,
+        rows: Vec<ImportRow>,
+        dry_run: bool,
+        principal: &Principal,
+    ) -> Result<ImportReport> {
+        let _timer = metrics::query_timer("import_loans");
+        let mut report = ImportReport {
+            dry_run,
+            rows: rows.len(),
+            inserted: 0,
+            updated: 0,
+            errors: Vec::new(),
+        };
 
-        let loans = query_builder
-            .build_query_as::<Loan>()
-            .fetch_all(pool)
+        let loan_ids: Vec<String> = rows
+            .iter()
+            .filter_map(|row| row.loan.as_ref().ok())
+            .map(|request| request.loan_id.clone())
+            .collect();
+
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+
+        // Lock the loans being replaced so their status checks hold until commit.
+        let existing: HashMap<String, (LoanStatus, String)> = sqlx::query!(
+            r#"SELECT loan_id, current_status AS "current_status: LoanStatus", servicer_name
+             FROM loans WHERE loan_id = ANY($1) FOR UPDATE"#,
+            &loan_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.loan_id, (row.current_status, row.servicer_name)))
+        .collect();
+
+        let mut seen = HashSet::new();
+        let mut accepted = Vec::new();
+        for row in rows {
+            let request = match row.loan {
+                Ok(request) => request,
+                Err(error) => {
+                    report.errors.push(ImportRowError {
+                        line: row.line,
+                        loan_id: None,
+                        errors: vec![error],
+                    });
+                    continue;
+                }
+            };
+
+            let mut errors = request.fields.validate();
+            errors.extend(check_scope(&principal.scope, &request.fields));
+            errors.extend(check_loan_id(&request.loan_id));
+            if !seen.insert(request.loan_id.clone()) {
+                errors.push(FieldError {
+                    field: "loanId",
+                    message: "appears more than once in the file".to_string(),
+                });
+            }
+
+            let current = existing.get(&request.loan_id);
+            match current {
+                Some((_, servicer_name)) if !principal.scope.permits(servicer_name) => {
+                    errors.push(FieldError {
+                        field: "loanId",
+                        message: "already exists for another servicer".to_string(),
+                    });
+                }
+                Some((current_status, _)) => {
+                    errors.extend(check_transition(*current_status, request.fields.current_status))
+                }
+                None => {}
+            }
+
+            if !errors.is_empty() {
+                report.errors.push(ImportRowError {
+                    line: row.line,
+                    loan_id: Some(request.loan_id),
+                    errors,
+                });
+                continue;
+            }
+
+            match current {
+                Some(_) => report.updated += 1,
+                None => report.inserted += 1,
+            }
+            accepted.push((row.line, request));
+        }
+
+        if dry_run || !report.errors.is_empty() {
+            return Ok(report);
+        }
+
+        for (line, request) in accepted {
+            let fields = &request.fields;
+            // Only loans locked above are replaced. One created by another request since then
+            // was never checked against the caller's scope or the lifecycle, so it is left alone.
+            let replace = existing.contains_key(&request.loan_id);
+            let written = sqlx::query!(
+                "INSERT INTO loans (
+                    loan_id, customer_name, property_address, origination_date, maturity_date,
+                    loan_amount, remaining_balance, interest_rate, monthly_payment, status,
+                    product_name, product_type, security_name, servicer_name, current_status
+                 ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                 ON CONFLICT (loan_id) DO UPDATE SET
+                    customer_name = EXCLUDED.customer_name,
+                    property_address = EXCLUDED.property_address,
+                    origination_date = EXCLUDED.origination_date,
+                    maturity_date = EXCLUDED.maturity_date,
+                    loan_amount = EXCLUDED.loan_amount,
+                    remaining_balance = EXCLUDED.remaining_balance,
+                    interest_rate = EXCLUDED.interest_rate,
+                    monthly_payment = EXCLUDED.monthly_payment,
+                    status = EXCLUDED.status,
+                    product_name = EXCLUDED.product_name,
+                    product_type = EXCLUDED.product_type,
+                    security_name = EXCLUDED.security_name,
+                    servicer_name = EXCLUDED.servicer_name,
+                    current_status = EXCLUDED.current_status
+                 WHERE $16",
+                request.loan_id,
+                fields.customer_name,
+                fields.property_address,
+                fields.origination_date,
+                fields.maturity_date,
+                fields.loan_amount,
+                fields.remaining_balance,
+                fields.interest_rate,
+                fields.monthly_payment,
+                fields.status.as_str(),
+                fields.product_name,
+                fields.product_type.as_str(),
+                fields.security_name,
+                fields.servicer_name,
+                fields.current_status.as_str(),
+                replace
+            )
+            .execute(&mut *tx)
             .await?;
+            if written.rows_affected() == 0 {
+                // Dropping the transaction rolls back the rows written so far.
+                report.errors.push(ImportRowError {
+                    line,
+                    loan_id: Some(request.loan_id),
+                    errors: vec![FieldError {
+                        field: "loanId",
+                        message: "was created by another request during the import".to_string(),
+                    }],
+                });
+                return Ok(report);
+            }
+
+            let from = existing.get(&request.loan_id).map(|(current_status, _)| *current_status);
+            let to = fields.current_status;
+            if from != Some(to) {
+                let changed_by = principal.user_id.as_str();
+                let reason = Some("Loan tape import");
+                Self::record_status_change(&mut tx, &request.loan_id, from, to, reason, changed_by)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    #[instrument(name = "LoanService::update_loan", skip_all, fields(%loan_id))]
+    pub async fn update_loan(
+        pool: &PgPool,
+        loan_id: &str,
+        request: UpdateLoanRequest,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<Loan>> {
+        let _timer = metrics::query_timer("update_loan");
+        let fields = request.fields;
+        Self::apply_write(pool, loan_id, request.updated_at, principal, |_| fields).await
+    }
+
+    #[instrument(name = "LoanService::patch_loan", skip_all, fields(%loan_id))]
+    pub async fn patch_loan(
+        pool: &PgPool,
+        loan_id: &str,
+        request: PatchLoanRequest,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<Loan>> {
+        let _timer = metrics::query_timer("patch_loan");
+        let expected_updated_at = request.updated_at;
+        Self::apply_write(pool, loan_id, expected_updated_at, principal, |current| {
+            request.apply_to(current)
+        })
+        .await
+    }
+
+    #[instrument(name = "LoanService::delete_loan", skip_all, fields(%loan_id))]
+    pub async fn delete_loan(
+        pool: &PgPool,
+        loan_id: &str,
+        expected_updated_at: DateTime<Utc>,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<()>> {
+        let _timer = metrics::query_timer("delete_loan");
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+
+        let current = match Self::lock_loan(&mut tx, loan_id, &principal.scope).await? {
+            Some(loan) => loan,
+            None => return Ok(WriteOutcome::NotFound),
+        };
+        if current.updated_at != Some(expected_updated_at) {
+            return Ok(WriteOutcome::Conflict(Some(Box::new(current))));
+        }
+
+        let deleted = sqlx::query!("DELETE FROM loans WHERE loan_id = $1", loan_id)
+            .execute(&mut *tx)
+            .await;
+        match deleted {
+            Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+                return Ok(WriteOutcome::Blocked("Loan has posted payments"));
+            }
+            deleted => deleted?,
+        };
+        tx.commit().await?;
+
+        Ok(WriteOutcome::Applied(()))
+    }
 
-        // Get total count
-        let mut count_query = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM loans WHERE 1=1");
-        
-        // Add same filters for count
-        if let Some(customer_name) = &filters.customer_name {
-            count_query.push(" AND customer_name ILIKE ");
-            count_query.push_bind(format!("%{}%", customer_name));
+    /// Locks the loan row, checks the client's `updated_at` against it, then writes the
+    /// fields produced by `build` once they pass validation.
+    async fn apply_write<F>(
+        pool: &PgPool,
+        loan_id: &str,
+        expected_updated_at: DateTime<Utc>,
+        principal: &Principal,
+        build: F,
+    ) -> Result<WriteOutcome<Loan>>
+    where
+        F: FnOnce(Loan) -> LoanFields,
+    {
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+
+        let current = match Self::lock_loan(&mut tx, loan_id, &principal.scope).await? {
+            Some(loan) => loan,
+            None => return Ok(WriteOutcome::NotFound),
+        };
+        if current.updated_at != Some(expected_updated_at) {
+            return Ok(WriteOutcome::Conflict(Some(Box::new(current))));
         }
 
-        if let Some(status) = &filters.status {
-            count_query.push(" AND status = ");
-            count_query.push_bind(status);
+        let from = current.current_status;
+        let fields = build(current);
+        let mut errors = fields.validate();
+        errors.extend(check_scope(&principal.scope, &fields));
+        if errors.is_empty() {
+            errors.extend(check_transition(from, fields.current_status));
+        }
+        if !errors.is_empty() {
+            return Ok(WriteOutcome::Invalid(errors));
         }
 
-        if let Some(product_type) = &filters.product_type {
-            count_query.push(" AND product_type = ");
-            count_query.push_bind(product_type);
+        let updated = sqlx::query_as!(
+            Loan,
+            r#"UPDATE loans SET
+                customer_name = $2, property_address = $3, origination_date = $4,
+                maturity_date = $5, loan_amount = $6, remaining_balance = $7,
+                interest_rate = $8, monthly_payment = $9, status = $10,
+                product_name = $11, product_type = $12, security_name = $13,
+                servicer_name = $14, current_status = $15
+             WHERE loan_id = $1
+             RETURNING loan_id, customer_name, property_address, origination_date,
+             maturity_date, loan_amount, remaining_balance, interest_rate,
+             monthly_payment, status AS "status: StatusCategory", product_name,
+             product_type AS "product_type: ProductType", security_name, servicer_name,
+             current_status AS "current_status: LoanStatus", updated_at"#,
+            loan_id,
+            fields.customer_name,
+            fields.property_address,
+            fields.origination_date,
+            fields.maturity_date,
+            fields.loan_amount,
+            fields.remaining_balance,
+            fields.interest_rate,
+            fields.monthly_payment,
+            fields.status.as_str(),
+            fields.product_name,
+            fields.product_type.as_str(),
+            fields.security_name,
+            fields.servicer_name,
+            fields.current_status.as_str()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if from != updated.current_status {
+            Self::record_status_change(
+                &mut tx,
+                loan_id,
+                Some(from),
+                updated.current_status,
+                None,
+                &principal.user_id,
+            )
+            .await?;
         }
+        tx.commit().await?;
 
-        if let Some(servicer_name) = &filters.servicer_name {
-            count_query.push(" AND servicer_name ILIKE ");
-            count_query.push_bind(format!("%{}%", servicer_name));
+        Ok(WriteOutcome::Applied(updated))
+    }
+
+    #[instrument(name = "LoanService::transition_status", skip_all, fields(%loan_id))]
+    pub async fn transition_status(
+        pool: &PgPool,
+        loan_id: &str,
+        request: StatusTransitionRequest,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<Loan>> {
+        let _timer = metrics::query_timer("transition_status");
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+
+        let current = match Self::lock_loan(&mut tx, loan_id, &principal.scope).await? {
+            Some(loan) => loan,
+            None => return Ok(WriteOutcome::NotFound),
+        };
+        if current.updated_at != Some(request.updated_at) {
+            return Ok(WriteOutcome::Conflict(Some(Box::new(current))));
         }
 
-        if let Some(min_amount) = filters.min_loan_amount {
-            count_query.push(" AND loan_amount >= ");
-            count_query.push_bind(min_amount);
+        let errors = check_transition(current.current_status, request.status);
+        if !errors.is_empty() {
+            return Ok(WriteOutcome::Invalid(errors));
+        }
+
+        let updated = Self::set_status(&mut tx, loan_id, request.status).await?;
+        Self::record_status_change(
+            &mut tx,
+            loan_id,
+            Some(current.current_status),
+            request.status,
+            request.reason.as_deref(),
+            &principal.user_id,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(WriteOutcome::Applied(updated))
+    }
+
+    #[instrument(name = "LoanService::get_status_history", skip_all, fields(%loan_id))]
+    pub async fn get_status_history(
+        pool: &PgPool,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<Vec<LoanStatusChange>>> {
+        let _timer = metrics::query_timer("get_status_history");
+        if Self::fetch_loan(pool, loan_id, scope).await?.is_none() {
+            return Ok(None);
+        }
+
+        let history = sqlx::query_as!(
+            LoanStatusChange,
+            "SELECT history_id, loan_id, from_status, from_current_status, to_status,
+             to_current_status, reason, changed_by, changed_at
+             FROM loan_status_history WHERE loan_id = $1
+             ORDER BY changed_at, history_id",
+            loan_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Some(history))
+    }
+
+    /// Field-level changes recorded by the `audit_loans_changes` trigger, oldest first.
+    /// Entries are kept after a loan is deleted, so an unknown loan yields an empty trail.
+    #[instrument(name = "LoanService::get_audit_trail", skip_all, fields(%loan_id))]
+    pub async fn get_audit_trail(
+        pool: &PgPool,
+        loan_id: &str,
+        filters: AuditFilters,
+        scope: &ServicerScope,
+    ) -> Result<Vec<AuditEntry>> {
+        let _timer = metrics::query_timer("get_audit_trail");
+        // A scoped caller only sees the trail of loans currently in its scope.
+        if scope.servicer_name().is_some()
+            && Self::fetch_loan(pool, loan_id, scope).await?.is_none()
+        {
+            return Ok(Vec::new());
         }
 
-        if let Some(max_amount) = filters.max_loan_amount {
-            count_query.push(" AND loan_amount <= ");
-            count_query.push_bind(max_amount);
+        let mut query_builder = sqlx::QueryBuilder::new(
+            "SELECT audit_id, loan_id, action, field_name, old_value, new_value,
+             changed_by, transaction_id, changed_at FROM loan_audit_log WHERE loan_id = "
+        );
+        query_builder.push_bind(loan_id);
+
+        if let Some(changed_by) = &filters.changed_by {
+            query_builder.push(" AND changed_by = ");
+            query_builder.push_bind(changed_by);
         }
 
-        if let Some(date_from) = filters.origination_date_from {
-            count_query.push(" AND origination_date >= ");
-            count_query.push_bind(date_from);
+        if let Some(changed_from) = filters.changed_from {
+            query_builder.push(" AND changed_at >= ");
+            query_builder.push_bind(changed_from);
         }
 
-        if let Some(date_to) = filters.origination_date_to {
-            count_query.push(" AND origination_date <= ");
-            count_query.push_bind(date_to);
+        if let Some(changed_to) = filters.changed_to {
+            query_builder.push(" AND changed_at <= ");
+            query_builder.push_bind(changed_to);
         }
 
-        let total: (i64,) = count_query
-            .build_query_as()
-            .fetch_one(pool)
+        query_builder.push(" ORDER BY changed_at, audit_id");
+
+        let entries = query_builder
+            .build_query_as::<AuditEntry>()
+            .fetch_all(pool)
             .await?;
 
-        Ok(SearchResponse {
-            data: loans,
-            total: total.0,
-            page,
-            page_size,
-        })
+        Ok(entries)
     }
 
-    pub async fn get_loan_by_id(pool: &PgPool, loan_id: &str) -> Result<Option<Loan>> {
+    /// Names the caller for the `audit_loans_changes` trigger for the rest of the transaction.
+    async fn set_actor(tx: &mut Transaction<'_, Postgres>, actor: &str) -> Result<()> {
+        sqlx::query("SELECT set_config('loan_api.user_id', $1, true)")
+            .bind(actor)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_status(
+        tx: &mut Transaction<'_, Postgres>,
+        loan_id: &str,
+        status: LoanStatus,
+    ) -> Result<Loan> {
         let loan = sqlx::query_as!(
             Loan,
-            "SELECT loan_id, customer_name, property_address, origination_date, 
-             maturity_date, loan_amount, remaining_balance, interest_rate, 
-             monthly_payment, status, product_name, product_type, 
-             security_name, servicer_name, current_status 
-             FROM loans WHERE loan_id = $1",
-            loan_id
+            r#"UPDATE loans SET status = $2, current_status = $3
+             WHERE loan_id = $1
+             RETURNING loan_id, customer_name, property_address, origination_date,
+             maturity_date, loan_amount, remaining_balance, interest_rate,
+             monthly_payment, status AS "status: StatusCategory", product_name,
+             product_type AS "product_type: ProductType", security_name, servicer_name,
+             current_status AS "current_status: LoanStatus", updated_at"#,
+            loan_id,
+            status.category().as_str(),
+            status.as_str()
         )
-        .fetch_optional(pool)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(loan)
+    }
+
+    async fn record_status_change(
+        tx: &mut Transaction<'_, Postgres>,
+        loan_id: &str,
+        from: Option<LoanStatus>,
+        to: LoanStatus,
+        reason: Option<&str>,
+        changed_by: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO loan_status_history (
+                loan_id, from_status, from_current_status, to_status, to_current_status,
+                reason, changed_by
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            loan_id,
+            from.map(|status| status.category().as_str()),
+            from.map(LoanStatus::as_str),
+            to.category().as_str(),
+            to.as_str(),
+            reason,
+            changed_by
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn lock_loan(
+        tx: &mut Transaction<'_, Postgres>,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<Loan>> {
+        let loan = sqlx::query_as!(
+            Loan,
+            r#"SELECT loan_id, customer_name, property_address, origination_date,
+             maturity_date, loan_amount, remaining_balance, interest_rate,
+             monthly_payment, status AS "status: StatusCategory", product_name,
+             product_type AS "product_type: ProductType", security_name, servicer_name,
+             current_status AS "current_status: LoanStatus", updated_at
+             FROM loans WHERE loan_id = $1 AND ($2::text IS NULL OR servicer_name = $2)
+             FOR UPDATE"#,
+            loan_id,
+            scope.servicer_name()
+        )
+        .fetch_optional(&mut **tx)
         .await?;
 
         Ok(loan)
     }
 
-    pub async fn get_statistics(pool: &PgPool) -> Result<Statistics> {
-        let basic_stats = sqlx::query!(
-            "SELECT 
-                COUNT(*) as total_loans,
-                COALESCE(SUM(loan_amount), 0) as total_loan_amount,
-                COALESCE(SUM(remaining_balance), 0) as total_remaining_balance,
-                COALESCE(AVG(interest_rate), 0) as average_interest_rate
-             FROM loans"
+    #[instrument(name = "LoanService::post_payment", skip_all, fields(%loan_id))]
+    pub async fn post_payment(
+        pool: &PgPool,
+        loan_id: &str,
+        request: PostPaymentRequest,
+        principal: &Principal,
+    ) -> Result<WriteOutcome<LoanPayment>> {
+        let _timer = metrics::query_timer("post_payment");
+        let mut tx = pool.begin().await?;
+        Self::set_actor(&mut tx, &principal.user_id).await?;
+
+        let loan = match Self::lock_loan(&mut tx, loan_id, &principal.scope).await? {
+            Some(loan) => loan,
+            None => return Ok(WriteOutcome::NotFound),
+        };
+
+        if loan.current_status.is_terminal() {
+            return Ok(WriteOutcome::Invalid(vec![FieldError {
+                field: "loanId",
+                message: format!(
+                    "cannot post payments to a loan that is {}",
+                    loan.current_status.as_str()
+                ),
+            }]));
+        }
+
+        let last_payment_date = sqlx::query_scalar!(
+            "SELECT MAX(payment_date) FROM loan_payments WHERE loan_id = $1",
+            loan_id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        let status_breakdown = sqlx::query!(
-            "SELECT status, COUNT(*) as count FROM loans GROUP BY status ORDER BY count DESC"
+        let split = match split_payment(&loan, &request, last_payment_date) {
+            Ok(split) => split,
+            Err(errors) => return Ok(WriteOutcome::Invalid(errors)),
+        };
+
+        let payment = sqlx::query_as!(
+            LoanPayment,
+            "INSERT INTO loan_payments (
+                payment_id, loan_id, payment_date, amount, interest_amount,
+                principal_amount, escrow_amount, fees_amount, balance_after
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING payment_id, loan_id, payment_date, amount, interest_amount,
+             principal_amount, escrow_amount, fees_amount, balance_after, created_at",
+            Uuid::new_v4(),
+            loan_id,
+            request.payment_date,
+            request.amount,
+            split.interest,
+            split.principal,
+            request.escrow_amount,
+            request.fees_amount,
+            split.balance_after
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE loans SET remaining_balance = $2 WHERE loan_id = $1",
+            loan_id,
+            split.balance_after
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if split.balance_after.is_zero() {
+            Self::set_status(&mut tx, loan_id, LoanStatus::PaidOff).await?;
+            Self::record_status_change(
+                &mut tx,
+                loan_id,
+                Some(loan.current_status),
+                LoanStatus::PaidOff,
+                Some("Paid in full"),
+                &principal.user_id,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(WriteOutcome::Applied(payment))
+    }
+
+    #[instrument(name = "LoanService::get_payments", skip_all, fields(%loan_id))]
+    pub async fn get_payments(
+        pool: &PgPool,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<Vec<LoanPayment>>> {
+        let _timer = metrics::query_timer("get_payments");
+        if Self::fetch_loan(pool, loan_id, scope).await?.is_none() {
+            return Ok(None);
+        }
+
+        let payments = sqlx::query_as!(
+            LoanPayment,
+            "SELECT payment_id, loan_id, payment_date, amount, interest_amount,
+             principal_amount, escrow_amount, fees_amount, balance_after, created_at
+             FROM loan_payments WHERE loan_id = $1
+             ORDER BY payment_date, created_at",
+            loan_id
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|row| StatusCount {
-            status: row.status,
-            count: row.count.unwrap_or(0),
-        })
-        .collect();
+        .await?;
+
+        Ok(Some(payments))
+    }
+
+    #[instrument(name = "LoanService::get_schedule", skip_all, fields(%loan_id))]
+    pub async fn get_schedule(
+        pool: &PgPool,
+        loan_id: &str,
+        scope: &ServicerScope,
+    ) -> Result<Option<AmortizationSchedule>> {
+        let _timer = metrics::query_timer("get_schedule");
+        let loan = Self::fetch_loan(pool, loan_id, scope).await?;
+        Ok(loan.map(|loan| amortization_schedule(&loan)))
+    }
 
-        let product_type_breakdown = sqlx::query!(
-            "SELECT product_type, COUNT(*) as count FROM loans GROUP BY product_type ORDER BY count DESC"
+    /// Loans per status across every servicer, for the monitoring gauges.
+    #[instrument(name = "LoanService::count_by_status", skip_all)]
+    pub async fn count_by_status(pool: &PgPool) -> Result<Vec<StatusCount>> {
+        let _timer = metrics::query_timer("count_by_status");
+        let counts = sqlx::query_as!(
+            StatusCount,
+            r#"SELECT status AS "status!", COUNT(*) AS "count!" FROM loans GROUP BY status"#
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|row| ProductTypeCount {
-            product_type: row.product_type,
-            count: row.count.unwrap_or(0),
-        })
-        .collect();
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Pool statistics for the loans matching the query, optionally broken down by
+    /// `group_by` with each dimension nested inside the previous one. Paging is ignored.
+    #[instrument(name = "LoanService::get_statistics", skip_all)]
+    pub async fn get_statistics(
+        pool: &PgPool,
+        query: LoanQuery,
+        group_by: &[StatisticsDimension],
+        scope: &ServicerScope,
+    ) -> Result<Statistics> {
+        let _timer = metrics::query_timer("get_statistics");
+        let mut tx = pool.begin().await?;
+        set_similarity_threshold(&mut tx, &query).await?;
+
+        let mut metrics_query = sqlx::QueryBuilder::new("SELECT ");
+        metrics_query.push(POOL_METRICS);
+        metrics_query.push(" FROM loans WHERE 1=1");
+        push_conditions(&mut metrics_query, &query, scope)?;
+        let metrics = metrics_query.build_query_as::<PoolMetrics>().fetch_one(&mut *tx).await?.rounded();
+
+        let mut breakdown_query = sqlx::QueryBuilder::new(
+            "SELECT GROUPING(status, product_type), COALESCE(status, product_type), COUNT(*)
+             FROM loans WHERE 1=1",
+        );
+        push_conditions(&mut breakdown_query, &query, scope)?;
+        breakdown_query.push(" GROUP BY GROUPING SETS ((status), (product_type)) ORDER BY 3 DESC");
+        let breakdowns: Vec<(i32, Option<String>, i64)> =
+            breakdown_query.build_query_as().fetch_all(&mut *tx).await?;
+
+        let mut status_breakdown = Vec::new();
+        let mut product_type_breakdown = Vec::new();
+        for (grouping, value, count) in breakdowns {
+            let value = value.unwrap_or_default();
+            match grouping {
+                0b01 => status_breakdown.push(StatusCount { status: value, count }),
+                _ => product_type_breakdown.push(ProductTypeCount { product_type: value, count }),
+            }
+        }
+
+        let groups = if group_by.is_empty() {
+            None
+        } else {
+            Some(Self::group_statistics(&mut tx, &query, group_by, scope).await?)
+        };
+
+        tx.commit().await?;
 
         Ok(Statistics {
-            total_loans: basic_stats.total_loans.unwrap_or(0),
-            total_loan_amount: basic_stats.total_loan_amount.unwrap_or(0.0),
-            total_remaining_balance: basic_stats.total_remaining_balance.unwrap_or(0.0),
-            average_interest_rate: basic_stats.average_interest_rate.unwrap_or(0.0),
+            metrics,
             status_breakdown,
             product_type_breakdown,
+            groups,
         })
     }
+
+    /// Builds the nested breakdown from one `ROLLUP` query, which returns a row for every
+    /// group at every level; `GROUPING()` tells how many leading dimensions a row is for.
+    async fn group_statistics(
+        tx: &mut Transaction<'_, Postgres>,
+        query: &LoanQuery,
+        group_by: &[StatisticsDimension],
+        scope: &ServicerScope,
+    ) -> Result<Vec<StatisticsGroup>> {
+        let expressions: Vec<&str> = group_by.iter().map(|&dim| dimension_sql(dim)).collect();
+        let dimensions = expressions.join(", ");
+
+        let mut groups_query = sqlx::QueryBuilder::new("SELECT ");
+        for (i, expression) in expressions.iter().enumerate() {
+            groups_query.push(format!("{} AS group_{}, ", expression, i));
+        }
+        groups_query.push(format!("GROUPING({}) AS grouping, ", dimensions));
+        groups_query.push(POOL_METRICS);
+        groups_query.push(" FROM loans WHERE 1=1");
+        push_conditions(&mut groups_query, query, scope)?;
+        groups_query.push(format!(
+            " GROUP BY ROLLUP({}) ORDER BY grouping DESC, total_remaining_balance DESC",
+            dimensions
+        ));
+        let rows = groups_query.build().fetch_all(&mut **tx).await?;
+
+        let mut groups: Vec<StatisticsGroup> = Vec::new();
+        for row in rows {
+            let grouping: i32 = row.try_get("grouping")?;
+            let depth = group_by.len() - (grouping.count_ones() as usize);
+            if depth == 0 {
+                continue;
+            }
+
+            // Parents sort before their children, so the path down to this row already exists.
+            let mut siblings = &mut groups;
+            for level in 0..depth - 1 {
+                let value: String = row.try_get(format!("group_{}", level).as_str())?;
+                siblings = match siblings.iter_mut().position(|group| group.value == value) {
+                    Some(i) => &mut siblings[i].groups,
+                    None => bail!("Statistics group {} is missing its parent", value),
+                };
+            }
+            siblings.push(StatisticsGroup {
+                dimension: group_by[depth - 1].name(),
+                value: row.try_get(format!("group_{}", depth - 1).as_str())?,
+                metrics: PoolMetrics::from_row(&row)?.rounded(),
+                groups: Vec::new(),
+            });
+        }
+
+        Ok(groups)
+    }
+
+    /// One time series over the loans matching the query, bucketed by `range`.
+    #[instrument(name = "LoanService::get_trend", skip_all)]
+    pub async fn get_trend(
+        pool: &PgPool,
+        metric: TrendMetric,
+        query: LoanQuery,
+        range: &TrendRange,
+        scope: &ServicerScope,
+    ) -> Result<Trend> {
+        let _timer = metrics::query_timer("get_trend");
+        let mut tx = pool.begin().await?;
+        set_similarity_threshold(&mut tx, &query).await?;
+
+        let trend = match metric {
+            TrendMetric::Originations => {
+                Self::origination_trend(&mut tx, &query, range, scope).await?
+            }
+            TrendMetric::Runoff => Self::runoff_trend(&mut tx, &query, range, scope).await?,
+            TrendMetric::Delinquency => {
+                Self::delinquency_trend(&mut tx, &query, range, scope).await?
+            }
+        };
+
+        tx.commit().await?;
+        Ok(trend)
+    }
+
+    /// Number and amount of the matching loans originated in each bucket.
+    async fn origination_trend(
+        tx: &mut Transaction<'_, Postgres>,
+        query: &LoanQuery,
+        range: &TrendRange,
+        scope: &ServicerScope,
+    ) -> Result<Trend> {
+        let mut trend_query = sqlx::QueryBuilder::new(
+            "WITH matching AS (SELECT origination_date, loan_amount FROM loans
+             WHERE origination_date BETWEEN ",
+        );
+        trend_query.push_bind(range.from);
+        trend_query.push(" AND ");
+        trend_query.push_bind(range.to);
+        push_conditions(&mut trend_query, query, scope)?;
+        trend_query.push(
+            ") SELECT COUNT(m.origination_date), COALESCE(SUM(m.loan_amount), 0)
+             FROM unnest(",
+        );
+        trend_query.push_bind(&range.buckets);
+        trend_query.push(format!(
+            "::date[]) AS b(bucket)
+             LEFT JOIN matching m ON date_trunc('{}', m.origination_date)::date = b.bucket
+             GROUP BY b.bucket ORDER BY b.bucket",
+            range.interval.name()
+        ));
+        let rows: Vec<(i64, Decimal)> = trend_query.build_query_as().fetch_all(&mut **tx).await?;
+
+        let (counts, volumes) =
+            rows.into_iter().map(|(count, volume)| (Decimal::from(count), volume)).unzip();
+        Ok(Trend::new(
+            range,
+            vec![
+                TrendSeries { name: "count", data: counts },
+                TrendSeries { name: "volume", data: volumes },
+            ],
+        ))
+    }
+
+    /// Contractual balance of the matching open loans at the end of each bucket, assuming
+    /// level monthly payments from this month that pay the balance off by `maturity_date`,
+    /// and the principal scheduled to run off within the bucket. Loans past maturity are
+    /// due in full this month. Each loan's balance is rounded to cents before summing.
+    async fn runoff_trend(
+        tx: &mut Transaction<'_, Postgres>,
+        query: &LoanQuery,
+        range: &TrendRange,
+        scope: &ServicerScope,
+    ) -> Result<Trend> {
+        let current_month = month_index(Utc::now().date_naive());
+        // Payments made by the start of each bucket, then by the end of the last one.
+        let mut payments: Vec<i32> = range
+            .buckets
+            .iter()
+            .map(|&start| (month_index(start) - current_month).max(0))
+            .collect();
+        if let Some(&last) = range.buckets.last() {
+            let months = range.interval.months() as i32;
+            payments.push((month_index(last) + months - current_month).max(0));
+        }
+
+        let mut trend_query = sqlx::QueryBuilder::new(
+            "SELECT remaining_balance, interest_rate, maturity_date FROM loans
+             WHERE remaining_balance > 0 AND status NOT IN ('Paid Off', 'Charged Off')",
+        );
+        push_conditions(&mut trend_query, query, scope)?;
+
+        let mut balances = vec![Decimal::ZERO; payments.len()];
+        let mut rows = trend_query
+            .build_query_as::<(Decimal, Decimal, NaiveDate)>()
+            .fetch(&mut **tx);
+        while let Some((balance, interest_rate, maturity_date)) = rows.try_next().await? {
+            let term = (month_index(maturity_date) - current_month + 1).max(1);
+            let schedule = LevelSchedule::new(balance, money::monthly_rate(interest_rate), term);
+            for (total, balance) in balances.iter_mut().zip(schedule.balances(&payments)) {
+                *total += balance;
+            }
+        }
+
+        let scheduled_balance = balances[1..].iter().copied().map(money::round_money).collect();
+        let runoff = balances.windows(2).map(|pair| money::round_money(pair[0] - pair[1])).collect();
+        Ok(Trend::new(
+            range,
+            vec![
+                TrendSeries { name: "scheduled_balance", data: scheduled_balance },
+                TrendSeries { name: "scheduled_runoff", data: runoff },
+            ],
+        ))
+    }
+
+    /// Share of the matching outstanding loans that were Delinquent or in Default at the end
+    /// of each bucket, reconstructed from the status history. Loans with no history are
+    /// taken to have always had their current status.
+    async fn delinquency_trend(
+        tx: &mut Transaction<'_, Postgres>,
+        query: &LoanQuery,
+        range: &TrendRange,
+        scope: &ServicerScope,
+    ) -> Result<Trend> {
+        let mut trend_query = sqlx::QueryBuilder::new(
+            "WITH matching AS (SELECT loan_id, origination_date, status FROM loans WHERE 1=1",
+        );
+        push_conditions(&mut trend_query, query, scope)?;
+        trend_query.push("), buckets AS (SELECT bucket, (bucket + ");
+        trend_query.push(format!(
+            "interval '{} months')::date AS bucket_end FROM unnest(",
+            range.interval.months()
+        ));
+        trend_query.push_bind(&range.buckets);
+        // Status at the end of a bucket: the last change before it, else the status the
+        // first later change moved away from. A first change that created the loan has no
+        // from_status, so the loan did not exist yet and is left out.
+        trend_query.push(
+            "::date[]) AS b(bucket)
+            ), statuses AS (
+                SELECT b.bucket, CASE
+                    WHEN NOT EXISTS (SELECT 1 FROM loan_status_history h WHERE h.loan_id = m.loan_id)
+                        THEN m.status
+                    ELSE COALESCE(
+                        (SELECT h.to_status FROM loan_status_history h
+                         WHERE h.loan_id = m.loan_id AND h.changed_at < b.bucket_end
+                         ORDER BY h.changed_at DESC, h.history_id DESC LIMIT 1),
+                        (SELECT h.from_status FROM loan_status_history h
+                         WHERE h.loan_id = m.loan_id
+                         ORDER BY h.changed_at, h.history_id LIMIT 1))
+                    END AS status
+                FROM buckets b JOIN matching m ON m.origination_date < b.bucket_end
+            )
+            SELECT COUNT(s.status) FILTER (WHERE s.status NOT IN ('Paid Off', 'Charged Off')),
+                COUNT(s.status) FILTER (WHERE s.status = 'Delinquent'),
+                COUNT(s.status) FILTER (WHERE s.status = 'Default')
+            FROM buckets b LEFT JOIN statuses s ON s.bucket = b.bucket
+            GROUP BY b.bucket ORDER BY b.bucket",
+        );
+        let rows: Vec<(i64, i64, i64)> = trend_query.build_query_as().fetch_all(&mut **tx).await?;
+
+        let mut outstanding = Vec::new();
+        let mut delinquent = Vec::new();
+        let mut default = Vec::new();
+        let mut rate = Vec::new();
+        for (open, late, defaulted) in rows {
+            outstanding.push(Decimal::from(open));
+            delinquent.push(Decimal::from(late));
+            default.push(Decimal::from(defaulted));
+            rate.push(if open == 0 {
+                Decimal::ZERO
+            } else {
+                money::round_ratio(Decimal::from(late + defaulted) / Decimal::from(open))
+            });
+        }
+        Ok(Trend::new(
+            range,
+            vec![
+                TrendSeries { name: "delinquency_rate", data: rate },
+                TrendSeries { name: "outstanding", data: outstanding },
+                TrendSeries { name: "delinquent", data: delinquent },
+                TrendSeries { name: "default", data: default },
+            ],
+        ))
+    }
+}
+
+/// Select list of [`PoolMetrics`], exact NUMERIC until [`PoolMetrics::rounded`].
+/// Balance-weighted averages fall back to 0 for a pool with no remaining balance.
+const POOL_METRICS: &str = "COUNT(*) AS total_loans,
+    COALESCE(SUM(loan_amount), 0) AS total_loan_amount,
+    COALESCE(SUM(remaining_balance), 0) AS total_remaining_balance,
+    COALESCE(AVG(interest_rate), 0) AS average_interest_rate,
+    COALESCE(SUM(interest_rate * remaining_balance)
+        / NULLIF(SUM(remaining_balance), 0), 0) AS weighted_average_coupon,
+    COALESCE(SUM(GREATEST(
+            EXTRACT(YEAR FROM age(maturity_date, CURRENT_DATE)) * 12
+            + EXTRACT(MONTH FROM age(maturity_date, CURRENT_DATE)), 0) * remaining_balance)
+        / NULLIF(SUM(remaining_balance), 0), 0) AS weighted_average_maturity,
+    COALESCE(SUM(GREATEST(
+            EXTRACT(YEAR FROM age(CURRENT_DATE, origination_date)) * 12
+            + EXTRACT(MONTH FROM age(CURRENT_DATE, origination_date)), 0) * remaining_balance)
+        / NULLIF(SUM(remaining_balance), 0), 0) AS weighted_average_loan_age,
+    COALESCE(SUM(remaining_balance) / NULLIF(SUM(loan_amount), 0), 0) AS pool_factor";
+
+/// Months since year 0, so differences count calendar months.
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+/// A balance paid off by `term` level monthly payments at `monthly_rate`, as projected
+/// by `runoff_trend`.
+struct LevelSchedule {
+    balance: Decimal,
+    monthly_rate: Decimal,
+    term: i32,
+    /// `(1 + monthly_rate)^term`, or None when it overflows and the payments are all
+    /// interest until the last.
+    growth: Option<Decimal>,
+}
+
+impl LevelSchedule {
+    fn new(balance: Decimal, monthly_rate: Decimal, term: i32) -> LevelSchedule {
+        let growth = (Decimal::ONE + monthly_rate).checked_powi(i64::from(term));
+        LevelSchedule { balance, monthly_rate, term, growth }
+    }
+
+    /// Balance in cents after each of `payments`, which must not decrease. The growth
+    /// factor is carried from one count to the next rather than raised afresh.
+    fn balances<'a>(&'a self, payments: &'a [i32]) -> impl Iterator<Item = Decimal> + 'a {
+        let factor = Decimal::ONE + self.monthly_rate;
+        // None at a zero rate, where the growth is 1 and the balance runs off linearly.
+        let per_growth =
+            self.growth.and_then(|growth| self.balance.checked_div(growth - Decimal::ONE));
+        let mut made = 0;
+        let mut growth_made = Decimal::ONE;
+
+        payments.iter().map(move |&count| {
+            if count >= self.term {
+                return Decimal::ZERO;
+            }
+            if self.monthly_rate.is_zero() {
+                let remaining = Decimal::from(self.term - count) / Decimal::from(self.term);
+                return money::round_money(self.balance * remaining);
+            }
+            let (Some(growth), Some(per_growth)) = (self.growth, per_growth) else {
+                return self.balance;
+            };
+            growth_made *= factor.powi(i64::from(count - made));
+            made = count;
+            money::round_money(per_growth * (growth - growth_made))
+        })
+    }
+}
+
+fn dimension_sql(dimension: StatisticsDimension) -> &'static str {
+    match dimension {
+        StatisticsDimension::Status => "status",
+        StatisticsDimension::CurrentStatus => "current_status",
+        StatisticsDimension::ProductType => "product_type",
+        StatisticsDimension::Servicer => "servicer_name",
+        StatisticsDimension::SecurityName => "security_name",
+        // Addresses end in "City, ST 12345"; anything else is grouped as Unknown.
+        StatisticsDimension::State => {
+            "COALESCE(substring(property_address FROM ',\\s*([A-Z]{2})\\s+\\d{5}(?:-\\d{4})?\\s*$'), 'Unknown')"
+        }
+    }
+}
+
+/// Text that `q` highlights are cut from, HTML-escaped so only the `<mark>` tags are markup.
+const HEADLINE_DOCUMENT: &str = "replace(replace(replace(
+    concat_ws(' | ', customer_name, property_address, product_name, security_name),
+    '&', '&amp;'), '<', '&lt;'), '>', '&gt;')";
+
+/// The similarity threshold is a setting read by the indexable `<%` operator, so it is set
+/// for the search's transaction only.
+async fn set_similarity_threshold(
+    tx: &mut Transaction<'_, Postgres>,
+    query: &LoanQuery,
+) -> Result<()> {
+    if query.similar.is_some() {
+        let threshold = query.min_similarity.unwrap_or(LoanQuery::DEFAULT_MIN_SIMILARITY);
+        sqlx::query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)")
+            .bind(threshold.to_string())
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn push_sort(query_builder: &mut sqlx::QueryBuilder<'_, Postgres>, sort: &[SortKey], reverse: bool) {
+    for (i, key) in sort.iter().enumerate() {
+        if i > 0 {
+            query_builder.push(", ");
+        }
+        query_builder.push(key.column.column());
+        query_builder.push(if key.descending != reverse { " DESC" } else { " ASC" });
+    }
+}
+
+/// The `WHERE` conditions shared by a search's page and facet queries.
+fn push_conditions(
+    query_builder: &mut sqlx::QueryBuilder<'_, Postgres>,
+    query: &LoanQuery,
+    scope: &ServicerScope,
+) -> Result<()> {
+    push_scope(query_builder, scope);
+
+    if let Some(q) = &query.q {
+        query_builder.push(" AND search_vector @@ websearch_to_tsquery('simple', ");
+        query_builder.push_bind(q.clone());
+        query_builder.push(")");
+    }
+
+    if let Some(similar) = &query.similar {
+        query_builder.push(" AND (");
+        query_builder.push_bind(similar.clone());
+        query_builder.push(" <% customer_name OR ");
+        query_builder.push_bind(similar.clone());
+        query_builder.push(" <% property_address)");
+    }
+
+    if let Some(filter) = &query.filter {
+        query_builder.push(" AND ");
+        push_filter(query_builder, filter)?;
+    }
+
+    Ok(())
+}
+
+/// Compiles a filter tree into SQL. Column names come from the [`LoanColumn`] allow-list and
+/// every value is bound, so nothing from the request is spliced into the statement.
+fn push_filter(
+    query_builder: &mut sqlx::QueryBuilder<'_, Postgres>,
+    filter: &LoanFilter,
+) -> Result<()> {
+    match filter {
+        LoanFilter::And(filters) | LoanFilter::Or(filters) if filters.is_empty() => {
+            query_builder.push(if matches!(filter, LoanFilter::And(_)) { "TRUE" } else { "FALSE" });
+        }
+        LoanFilter::And(filters) | LoanFilter::Or(filters) => {
+            let separator = if matches!(filter, LoanFilter::And(_)) { " AND " } else { " OR " };
+            query_builder.push("(");
+            for (i, filter) in filters.iter().enumerate() {
+                if i > 0 {
+                    query_builder.push(separator);
+                }
+                push_filter(query_builder, filter)?;
+            }
+            query_builder.push(")");
+        }
+        LoanFilter::Not(filter) => {
+            query_builder.push("NOT ");
+            push_filter(query_builder, filter)?;
+        }
+        LoanFilter::Eq { column, value } => {
+            query_builder.push(column.column());
+            query_builder.push(" = ");
+            push_column_value(query_builder, *column, value)?;
+        }
+        LoanFilter::In { column, values } if values.is_empty() => {
+            query_builder.push("FALSE");
+        }
+        LoanFilter::In { column, values } => {
+            query_builder.push(column.column());
+            query_builder.push(" IN (");
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    query_builder.push(", ");
+                }
+                push_column_value(query_builder, *column, value)?;
+            }
+            query_builder.push(")");
+        }
+        LoanFilter::Range { column, min, max } => {
+            query_builder.push("(TRUE");
+            if let Some(min) = min {
+                query_builder.push(" AND ");
+                query_builder.push(column.column());
+                query_builder.push(" >= ");
+                push_column_value(query_builder, *column, min)?;
+            }
+            if let Some(max) = max {
+                query_builder.push(" AND ");
+                query_builder.push(column.column());
+                query_builder.push(" <= ");
+                push_column_value(query_builder, *column, max)?;
+            }
+            query_builder.push(")");
+        }
+        LoanFilter::Contains { column, value } => {
+            query_builder.push(column.column());
+            query_builder.push(" ILIKE ");
+            query_builder.push_bind(format!("%{}%", escape_like(value)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Escapes `LIKE` wildcards so that user input only ever matches literally.
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Restricts the query to rows strictly past the cursor row in sort order, i.e.
+/// `(k1 > v1) OR (k1 = v1 AND k2 > v2) OR ...` with `<` for descending keys.
+fn push_keyset(
+    query_builder: &mut sqlx::QueryBuilder<'_, Postgres>,
+    sort: &[SortKey],
+    cursor: &SearchCursor,
+) -> Result<()> {
+    if cursor.values.len() != sort.len() {
+        bail!("Search cursor does not match the sort");
+    }
+
+    query_builder.push(" AND (");
+    for i in 0..sort.len() {
+        if i > 0 {
+            query_builder.push(" OR ");
+        }
+        query_builder.push("(");
+        for (key, value) in sort.iter().zip(&cursor.values).take(i) {
+            query_builder.push(key.column.column());
+            query_builder.push(" = ");
+            push_column_value(query_builder, key.column, value)?;
+            query_builder.push(" AND ");
+        }
+        let key = sort[i];
+        query_builder.push(key.column.column());
+        query_builder.push(if key.descending != cursor.backward { " < " } else { " > " });
+        push_column_value(query_builder, key.column, &cursor.values[i])?;
+        query_builder.push(")");
+    }
+    query_builder.push(")");
+
+    Ok(())
+}
+
+fn push_column_value(
+    query_builder: &mut sqlx::QueryBuilder<'_, Postgres>,
+    column: LoanColumn,
+    value: &serde_json::Value,
+) -> Result<()> {
+    let invalid = || anyhow!("Invalid value for {}", column.column());
+
+    match column.kind() {
+        ColumnKind::Date => {
+            let date = value.as_str().and_then(|date| date.parse::<NaiveDate>().ok());
+            query_builder.push_bind(date.ok_or_else(invalid)?);
+        }
+        ColumnKind::Number => {
+            query_builder.push_bind(money::from_json(value).ok_or_else(invalid)?);
+        }
+        ColumnKind::Text => {
+            query_builder.push_bind(value.as_str().ok_or_else(invalid)?.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn push_scope(query_builder: &mut sqlx::QueryBuilder<'_, Postgres>, scope: &ServicerScope) {
+    if let Some(servicer_name) = scope.servicer_name() {
+        query_builder.push(" AND servicer_name = ");
+        query_builder.push_bind(servicer_name.to_string());
+    }
+}
+
+/// Keeps scoped callers from creating loans for, or moving loans to, another servicer.
+fn check_scope(scope: &ServicerScope, fields: &LoanFields) -> Option<FieldError> {
+    if scope.permits(&fields.servicer_name) {
+        return None;
+    }
+
+    Some(FieldError {
+        field: "servicerName",
+        message: format!("must be {}", scope.servicer_name().unwrap_or_default()),
+    })
+}
+
+fn check_loan_id(loan_id: &str) -> Option<FieldError> {
+    let message = if loan_id.trim().is_empty() {
+        "must not be empty"
+    } else if loan_id.chars().count() > 50 {
+        "must be at most 50 characters"
+    } else {
+        return None;
+    };
+
+    Some(FieldError { field: "loanId", message: message.to_string() })
+}
+
+/// Rejects a status change that the loan lifecycle does not allow.
+fn check_transition(from: LoanStatus, to: LoanStatus) -> Vec<FieldError> {
+    if from == to || from.can_transition_to(to) {
+        return Vec::new();
+    }
+
+    vec![FieldError {
+        field: "status",
+        message: format!("cannot transition from {} to {}", from.as_str(), to.as_str()),
+    }]
+}
+
+struct PaymentSplit {
+    interest: Decimal,
+    principal: Decimal,
+    balance_after: Decimal,
+}
+
+/// Splits a payment into interest and principal after the escrow and fee portions.
+/// Interest accrues on the remaining balance (30/360) from the latest posted payment, or
+/// from origination before the first one, up to the payment date, rounded to cents.
+fn split_payment(
+    loan: &Loan,
+    request: &PostPaymentRequest,
+    last_payment_date: Option<NaiveDate>,
+) -> std::result::Result<PaymentSplit, Vec<FieldError>> {
+    let mut errors = Vec::new();
+    for (field, value) in [
+        ("amount", request.amount),
+        ("escrowAmount", request.escrow_amount),
+        ("feesAmount", request.fees_amount),
+    ] {
+        if value.is_sign_negative() && !value.is_zero() {
+            errors.push(FieldError { field, message: "must be a non-negative number".to_string() });
+        } else if !money::has_scale(value, money::MONEY_SCALE) {
+            errors.push(FieldError {
+                field,
+                message: format!("must have at most {} decimal places", money::MONEY_SCALE),
+            });
+        }
+    }
+    if request.amount <= Decimal::ZERO {
+        errors.push(FieldError { field: "amount", message: "must be greater than zero".to_string() });
+    }
+    if request.payment_date < loan.origination_date {
+        errors.push(FieldError {
+            field: "paymentDate",
+            message: "must not be before the loan's originationDate".to_string(),
+        });
+    }
+    if let Some(last_payment_date) = last_payment_date {
+        if request.payment_date < last_payment_date {
+            errors.push(FieldError {
+                field: "paymentDate",
+                message: format!(
+                    "must not be before the latest posted payment on {}",
+                    last_payment_date
+                ),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let available = request.amount - request.escrow_amount - request.fees_amount;
+    if available < Decimal::ZERO {
+        return Err(vec![FieldError {
+            field: "amount",
+            message: "must cover escrowAmount and feesAmount".to_string(),
+        }]);
+    }
+
+    let accrual_start = last_payment_date.unwrap_or(loan.origination_date);
+    let days = days_30_360(accrual_start, request.payment_date);
+    let interest_due = money::round_money(
+        loan.remaining_balance * money::monthly_rate(loan.interest_rate) * Decimal::from(days)
+            / Decimal::from(30),
+    );
+    let interest = available.min(interest_due);
+    let principal = available - interest;
+    if principal > loan.remaining_balance {
+        return Err(vec![FieldError {
+            field: "amount",
+            message: format!(
+                "exceeds the payoff amount of {}",
+                loan.remaining_balance + interest_due + request.escrow_amount + request.fees_amount
+            ),
+        }]);
+    }
+
+    Ok(PaymentSplit {
+        interest,
+        principal,
+        balance_after: loan.remaining_balance - principal,
+    })
+}
+
+/// Days between two dates under the 30/360 US (bond basis) convention, where every month
+/// has 30 days, so a payment one calendar month after the last accrues exactly a month.
+fn days_30_360(start: NaiveDate, end: NaiveDate) -> i64 {
+    let start_day = start.day().min(30);
+    let end_day = if start_day == 30 { end.day().min(30) } else { end.day() };
+    i64::from(end.year() - start.year()) * 360
+        + (i64::from(end.month()) - i64::from(start.month())) * 30
+        + i64::from(end_day)
+        - i64::from(start_day)
+}
+
+/// Builds the level-payment amortization table for the original loan terms, with one
+/// payment per month from the month after origination through maturity.
+fn amortization_schedule(loan: &Loan) -> AmortizationSchedule {
+    let origination = loan.origination_date;
+    let maturity = loan.maturity_date;
+    let mut months = (maturity.year() - origination.year()) * 12
+        + maturity.month() as i32
+        - origination.month() as i32;
+    if maturity.day() < origination.day() {
+        months -= 1;
+    }
+    let number_of_payments = months.max(1) as u32;
+
+    let monthly_rate = money::monthly_rate(loan.interest_rate);
+    let level_payment = if monthly_rate.is_zero() {
+        loan.loan_amount / Decimal::from(number_of_payments)
+    } else {
+        // Growth too large for a Decimal only happens for rates and terms where the
+        // payment has converged on the interest alone.
+        match (Decimal::ONE + monthly_rate).checked_powi(i64::from(number_of_payments)) {
+            Some(growth) => loan.loan_amount * monthly_rate * growth / (growth - Decimal::ONE),
+            None => loan.loan_amount * monthly_rate,
+        }
+    };
+    let level_payment = money::round_money(level_payment);
+
+    let mut balance = loan.loan_amount;
+    let mut total_interest = Decimal::ZERO;
+    let mut entries = Vec::with_capacity(number_of_payments as usize);
+    for period in 1..=number_of_payments {
+        let interest = money::round_money(balance * monthly_rate);
+        // The final payment absorbs the rounding drift so the loan ends at zero.
+        let principal = if period == number_of_payments {
+            balance
+        } else {
+            (level_payment - interest).min(balance)
+        };
+        balance -= principal;
+        total_interest += interest;
+
+        entries.push(ScheduleEntry {
+            period,
+            payment_date: origination
+                .checked_add_months(Months::new(period))
+                .unwrap_or(maturity),
+            payment: interest + principal,
+            interest,
+            principal,
+            ending_balance: balance,
+        });
+    }
+
+    AmortizationSchedule {
+        loan_id: loan.loan_id.clone(),
+        number_of_payments,
+        computed_monthly_payment: level_payment,
+        stored_monthly_payment: loan.monthly_payment,
+        payment_mismatch: (loan.monthly_payment - level_payment).abs() > Decimal::new(1, 2),
+        total_interest,
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::from_str(value).unwrap()
+    }
+
+    /// 200,000.00 at 6%, so a month of interest on the full balance is 1,000.00.
+    fn loan() -> Loan {
+        Loan {
+            loan_id: "LN-1".to_string(),
+            customer_name: "Jane Doe".to_string(),
+            property_address: "1 Main St, Springfield, IL".to_string(),
+            origination_date: date("2024-01-15"),
+            maturity_date: date("2054-01-15"),
+            loan_amount: dec("200000.00"),
+            remaining_balance: dec("200000.00"),
+            interest_rate: dec("6.000"),
+            monthly_payment: dec("1199.10"),
+            status: StatusCategory::Active,
+            product_name: "30 Year Fixed".to_string(),
+            product_type: ProductType::FixedRate,
+            security_name: "POOL-1".to_string(),
+            servicer_name: "Acme Servicing".to_string(),
+            current_status: LoanStatus::Current,
+            updated_at: None,
+        }
+    }
+
+    fn payment(payment_date: &str, amount: &str, escrow_amount: &str) -> PostPaymentRequest {
+        PostPaymentRequest {
+            payment_date: date(payment_date),
+            amount: dec(amount),
+            escrow_amount: dec(escrow_amount),
+            fees_amount: Decimal::ZERO,
+        }
+    }
+
+    fn error_fields(result: std::result::Result<PaymentSplit, Vec<FieldError>>) -> Vec<&'static str> {
+        match result {
+            Ok(_) => panic!("expected the payment to be rejected"),
+            Err(errors) => errors.iter().map(|error| error.field).collect(),
+        }
+    }
+
+    #[test]
+    fn payment_pays_escrow_then_interest_then_principal() {
+        let split = split_payment(&loan(), &payment("2024-02-15", "1500.00", "200.00"), None)
+            .unwrap();
+        assert_eq!(split.interest, dec("1000.00"));
+        assert_eq!(split.principal, dec("300.00"));
+        assert_eq!(split.balance_after, dec("199700.00"));
+    }
+
+    #[test]
+    fn interest_accrues_from_the_latest_payment() {
+        let last = Some(date("2024-02-01"));
+        let split = split_payment(&loan(), &payment("2024-02-16", "1000.00", "0"), last).unwrap();
+        assert_eq!(split.interest, dec("500.00"));
+        assert_eq!(split.principal, dec("500.00"));
+
+        let same_day = split_payment(&loan(), &payment("2024-02-01", "1000.00", "0"), last)
+            .unwrap();
+        assert_eq!(same_day.interest, Decimal::ZERO);
+        assert_eq!(same_day.principal, dec("1000.00"));
+    }
+
+    #[test]
+    fn partial_payment_only_covers_interest() {
+        let split = split_payment(&loan(), &payment("2024-02-15", "600.00", "0"), None).unwrap();
+        assert_eq!(split.interest, dec("600.00"));
+        assert_eq!(split.principal, Decimal::ZERO);
+        assert_eq!(split.balance_after, dec("200000.00"));
+    }
+
+    #[test]
+    fn payoff_pays_the_loan_to_zero() {
+        let split = split_payment(&loan(), &payment("2024-02-15", "201000.00", "0"), None)
+            .unwrap();
+        assert_eq!(split.principal, dec("200000.00"));
+        assert!(split.balance_after.is_zero());
+    }
+
+    #[test]
+    fn overpayment_is_rejected() {
+        let result = split_payment(&loan(), &payment("2024-02-15", "201000.01", "0"), None);
+        assert_eq!(error_fields(result), ["amount"]);
+    }
+
+    #[test]
+    fn payment_must_cover_escrow() {
+        let result = split_payment(&loan(), &payment("2024-02-15", "100.00", "200.00"), None);
+        assert_eq!(error_fields(result), ["amount"]);
+    }
+
+    #[test]
+    fn payment_before_the_latest_payment_is_rejected() {
+        let last = Some(date("2024-03-15"));
+        let result = split_payment(&loan(), &payment("2024-02-15", "1500.00", "0"), last);
+        assert_eq!(error_fields(result), ["paymentDate"]);
+    }
+
+    #[test]
+    fn schedule_final_payment_absorbs_rounding() {
+        // 1,000.00 at 12% over three months: a level payment of 340.0221... rounds to
+        // 340.02, leaving a cent for the last payment to pick up.
+        let mut short = loan();
+        short.loan_amount = dec("1000.00");
+        short.interest_rate = dec("12.000");
+        short.maturity_date = date("2024-04-15");
+        let schedule = amortization_schedule(&short);
+
+        assert_eq!(schedule.number_of_payments, 3);
+        assert_eq!(schedule.computed_monthly_payment, dec("340.02"));
+        let rows: Vec<_> = schedule
+            .entries
+            .iter()
+            .map(|entry| (entry.payment, entry.interest, entry.principal, entry.ending_balance))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (dec("340.02"), dec("10.00"), dec("330.02"), dec("669.98")),
+                (dec("340.02"), dec("6.70"), dec("333.32"), dec("336.66")),
+                (dec("340.03"), dec("3.37"), dec("336.66"), dec("0.00")),
+            ]
+        );
+        assert_eq!(schedule.total_interest, dec("20.07"));
+        assert_eq!(schedule.entries[2].payment_date, date("2024-04-15"));
+    }
+
+    #[test]
+    fn schedule_pays_the_loan_to_exactly_zero() {
+        let schedule = amortization_schedule(&loan());
+        assert_eq!(schedule.number_of_payments, 360);
+        assert_eq!(schedule.computed_monthly_payment, dec("1199.10"));
+        assert!(!schedule.payment_mismatch);
+
+        let last = schedule.entries.last().unwrap();
+        assert_eq!(last.ending_balance.to_string(), "0.00");
+        let principal: Decimal = schedule.entries.iter().map(|entry| entry.principal).sum();
+        assert_eq!(principal, dec("200000.00"));
+        let interest: Decimal = schedule.entries.iter().map(|entry| entry.interest).sum();
+        assert_eq!(interest, schedule.total_interest);
+        assert!(schedule
+            .entries
+            .iter()
+            .all(|entry| entry.payment == entry.interest + entry.principal));
+    }
+
+    #[test]
+    fn schedule_at_zero_rate_repays_principal_evenly() {
+        let mut interest_free = loan();
+        interest_free.loan_amount = dec("120000.00");
+        interest_free.interest_rate = dec("0.000");
+        interest_free.monthly_payment = dec("1000.00");
+        interest_free.maturity_date = date("2034-01-15");
+        let schedule = amortization_schedule(&interest_free);
+
+        assert_eq!(schedule.number_of_payments, 120);
+        assert_eq!(schedule.computed_monthly_payment, dec("1000.00"));
+        assert!(schedule.total_interest.is_zero());
+        assert!(schedule
+            .entries
+            .iter()
+            .all(|entry| entry.interest.is_zero() && entry.principal == dec("1000.00")));
+        assert!(schedule.entries.last().unwrap().ending_balance.is_zero());
+    }
+
+    #[test]
+    fn pool_metrics_round_to_their_reporting_scales() {
+        let metrics = PoolMetrics {
+            total_loans: 3,
+            total_loan_amount: dec("600000"),
+            total_remaining_balance: dec("200000.005"),
+            average_interest_rate: dec("5.2083333333333333"),
+            weighted_average_coupon: dec("5.875"),
+            weighted_average_maturity: dec("287.49995"),
+            weighted_average_loan_age: Decimal::ZERO,
+            pool_factor: Decimal::ONE / Decimal::from(3),
+        }
+        .rounded();
+        assert_eq!(
+            serde_json::to_value(metrics).unwrap(),
+            json!({
+                "total_loans": 3,
+                "total_loan_amount": "600000.00",
+                "total_remaining_balance": "200000.01",
+                "average_interest_rate": "5.2083",
+                "weighted_average_coupon": "5.8750",
+                "weighted_average_maturity": "287.5000",
+                "weighted_average_loan_age": "0.0000",
+                "pool_factor": "0.33333333",
+            })
+        );
+    }
+
+    #[test]
+    fn runoff_balances_amortize_to_zero() {
+        let flat = LevelSchedule::new(dec("1200.00"), Decimal::ZERO, 12);
+        let balances: Vec<Decimal> = flat.balances(&[0, 3, 11, 12]).collect();
+        assert_eq!(balances, [dec("1200.00"), dec("900.00"), dec("100.00"), Decimal::ZERO]);
+
+        let level = LevelSchedule::new(dec("200000.00"), money::monthly_rate(dec("6")), 360);
+        let balances: Vec<Decimal> = level.balances(&[0, 1, 359, 360]).collect();
+        // The first payment of 1,199.10 is 1,000.00 of interest and 199.10 of principal.
+        assert_eq!(balances, [dec("200000.00"), dec("199800.90"), dec("1193.14"), Decimal::ZERO]);
+    }
+
+    async fn database() -> PgPool {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        PgPool::connect(&url).await.expect("Failed to connect to DATABASE_URL")
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn numeric_round_trips_exactly() {
+        let pool = database().await;
+        for text in ["0.00", "0.01", "-12.34", "1199.10", "9999999999999.99"] {
+            let value = dec(text);
+            let stored: Decimal = sqlx::query_scalar("SELECT $1::NUMERIC(15,2)")
+                .bind(value)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(stored, value);
+            assert_eq!(money::round_money(stored).to_string(), text);
+        }
+
+        let rate: Decimal = sqlx::query_scalar("SELECT $1::NUMERIC(5,3)")
+            .bind(dec("4.125"))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rate, dec("4.125"));
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn pool_metrics_are_exact_aggregates() {
+        let pool = database().await;
+        let loans = "WITH loans (loan_amount, remaining_balance, interest_rate,
+                origination_date, maturity_date) AS (VALUES
+                (100000.00::NUMERIC(15,2), 50000.00::NUMERIC(15,2), 4.000::NUMERIC(5,3),
+                    DATE '2020-01-01', DATE '2050-01-01'),
+                (300000.00, 150000.00, 6.500, DATE '2021-06-15', DATE '2051-06-15'),
+                (200000.00, 0.00, 5.125, DATE '2019-03-01', DATE '2049-03-01'))";
+
+        let sql = format!("{} SELECT {} FROM loans", loans, POOL_METRICS);
+        let metrics = sqlx::query_as::<_, PoolMetrics>(&sql)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .rounded();
+        assert_eq!(metrics.total_loans, 3);
+        assert_eq!(metrics.total_loan_amount, dec("600000.00"));
+        assert_eq!(metrics.total_remaining_balance, dec("200000.00"));
+        // (4 + 6.5 + 5.125) / 3, unweighted.
+        assert_eq!(metrics.average_interest_rate, dec("5.2083"));
+        // (4 * 50,000 + 6.5 * 150,000) / 200,000; the paid-off loan carries no weight.
+        assert_eq!(metrics.weighted_average_coupon, dec("5.8750"));
+        assert_eq!(metrics.pool_factor, dec("0.33333333"));
+
+        let sql = format!("{} SELECT {} FROM loans WHERE false", loans, POOL_METRICS);
+        let empty = sqlx::query_as::<_, PoolMetrics>(&sql)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .rounded();
+        let empty = serde_json::to_value(empty).unwrap();
+        assert_eq!(empty["total_loan_amount"], "0.00");
+        assert_eq!(empty["weighted_average_coupon"], "0.0000");
+        assert_eq!(empty["pool_factor"], "0.00000000");
+    }
+
+    #[test]
+    fn transition_check_reports_the_rejected_change() {
+        assert!(check_transition(LoanStatus::Current, LoanStatus::ThirtyDaysLate).is_empty());
+        // Saving a loan without changing its status is not a transition.
+        assert!(check_transition(LoanStatus::PaidOff, LoanStatus::PaidOff).is_empty());
+
+        let errors = check_transition(LoanStatus::PaidOff, LoanStatus::Current);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "status");
+        assert_eq!(errors[0].message, "cannot transition from Paid Off to Current");
+    }
+
+    fn filter(json: serde_json::Value) -> LoanFilter {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn filter_sql(filter: &LoanFilter) -> String {
+        let mut query_builder = sqlx::QueryBuilder::new("");
+        push_filter(&mut query_builder, filter).unwrap();
+        query_builder.sql().to_string()
+    }
+
+    #[test]
+    fn every_column_compiles_to_a_bound_comparison() {
+        for column in LoanColumn::ALL {
+            let value = match (column.kind(), column.allowed_values()) {
+                (ColumnKind::Date, _) => json!("2024-01-15"),
+                (ColumnKind::Number, _) => json!("1.5"),
+                (ColumnKind::Text, Some(allowed)) => json!(allowed[0]),
+                (ColumnKind::Text, None) => json!("x"),
+            };
+            let eq = LoanFilter::Eq { column, value };
+            assert_eq!(filter_sql(&eq), format!("{} = $1", column.column()));
+        }
+    }
+
+    #[test]
+    fn operators_compile_to_sql() {
+        let cases = [
+            (json!({"eq": {"column": "loan_amount", "value": 250000}}),
+                "loan_amount = $1"),
+            (json!({"in": {"column": "status", "values": ["Delinquent", "Default"]}}),
+                "status IN ($1, $2)"),
+            (json!({"in": {"column": "status", "values": []}}), "FALSE"),
+            (json!({"range": {"column": "origination_date",
+                "min": "2024-01-01", "max": "2024-12-31"}}),
+                "(TRUE AND origination_date >= $1 AND origination_date <= $2)"),
+            (json!({"range": {"column": "interest_rate", "max": 5.5}}),
+                "(TRUE AND interest_rate <= $1)"),
+            (json!({"contains": {"column": "customer_name", "value": "doe"}}),
+                "customer_name ILIKE $1"),
+            (json!({"and": []}), "TRUE"),
+            (json!({"or": []}), "FALSE"),
+            (json!({"and": [
+                {"eq": {"column": "servicer_name", "value": "Acme"}},
+                {"or": [
+                    {"eq": {"column": "status", "value": "Default"}},
+                    {"not": {"eq": {"column": "current_status", "value": "Current"}}},
+                ]},
+            ]}), "(servicer_name = $1 AND (status = $2 OR NOT current_status = $3))"),
+        ];
+        for (json, sql) in cases {
+            assert_eq!(filter_sql(&filter(json.clone())), sql, "{}", json);
+        }
+    }
+
+    #[test]
+    fn filter_values_are_never_spliced_into_sql() {
+        let value = "x'; DROP TABLE loans; --";
+        let sql = filter_sql(&filter(json!({"or": [
+            {"eq": {"column": "customer_name", "value": value}},
+            {"contains": {"column": "property_address", "value": value}},
+        ]})));
+        assert_eq!(sql, "(customer_name = $1 OR property_address ILIKE $2)");
+        assert_eq!(escape_like("50%_off\\"), "50\\%\\_off\\\\");
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_do_not_compile() {
+        let mut query_builder = sqlx::QueryBuilder::new("");
+        let eq = filter(json!({"eq": {"column": "loan_amount", "value": "abc"}}));
+        assert!(push_filter(&mut query_builder, &eq).is_err());
+    }
+
+    #[test]
+    fn conditions_combine_scope_search_and_filter() {
+        let query: LoanQuery = serde_json::from_value(json!({
+            "q": "springfield",
+            "similar": "jane",
+            "filter": {"eq": {"column": "status", "value": "Active"}},
+        }))
+        .unwrap();
+        let mut query_builder = sqlx::QueryBuilder::new("WHERE 1=1");
+        push_conditions(&mut query_builder, &query, &ServicerScope::for_tests(Some("Acme")))
+            .unwrap();
+        assert_eq!(
+            query_builder.sql(),
+            "WHERE 1=1 AND servicer_name = $1 \
+             AND search_vector @@ websearch_to_tsquery('simple', $2) \
+             AND ($3 <% customer_name OR $4 <% property_address) AND status = $5"
+        );
+
+        let mut query_builder = sqlx::QueryBuilder::new("WHERE 1=1");
+        let unfiltered: LoanQuery = serde_json::from_value(json!({})).unwrap();
+        push_conditions(&mut query_builder, &unfiltered, &ServicerScope::for_tests(None)).unwrap();
+        assert_eq!(query_builder.sql(), "WHERE 1=1");
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn filters_bind_values_of_the_column_type() {
+        let pool = database().await;
+        let cases = [
+            (json!({"eq": {"column": "loan_amount", "value": 200000}}), true),
+            (json!({"eq": {"column": "loan_amount", "value": "200000.01"}}), false),
+            (json!({"in": {"column": "status", "values": ["Default", "Active"]}}), true),
+            (json!({"range": {"column": "origination_date",
+                "min": "2024-01-01", "max": "2024-01-31"}}), true),
+            (json!({"range": {"column": "origination_date", "min": "2024-02-01"}}),
+                false),
+            (json!({"range": {"column": "interest_rate", "max": 5.5}}), false),
+            (json!({"contains": {"column": "customer_name", "value": "DOE"}}), true),
+            (json!({"contains": {"column": "customer_name", "value": "J%"}}), false),
+            (json!({"not": {"eq": {"column": "servicer_name",
+                "value": "Acme Servicing"}}}), false),
+        ];
+        for (json, matches) in cases {
+            let mut query_builder = sqlx::QueryBuilder::new(
+                "WITH loans AS (SELECT 'LN-1'::VARCHAR AS loan_id,
+                    'Jane Doe'::VARCHAR AS customer_name,
+                    DATE '2024-01-15' AS origination_date,
+                    200000.00::NUMERIC(15,2) AS loan_amount,
+                    6.000::NUMERIC(5,3) AS interest_rate,
+                    'Active'::VARCHAR AS status,
+                    'Acme Servicing'::VARCHAR AS servicer_name)
+                 SELECT COUNT(*) FROM loans WHERE ",
+            );
+            push_filter(&mut query_builder, &filter(json.clone())).unwrap();
+            let count: i64 = query_builder.build_query_scalar().fetch_one(&pool).await.unwrap();
+            assert_eq!(count == 1, matches, "{}", json);
+        }
+    }
+
+    #[test]
+    fn days_count_thirty_per_month() {
+        assert_eq!(days_30_360(date("2024-01-15"), date("2024-02-15")), 30);
+        assert_eq!(days_30_360(date("2023-12-15"), date("2024-01-15")), 30);
+        assert_eq!(days_30_360(date("2024-01-31"), date("2024-03-31")), 60);
+        assert_eq!(days_30_360(date("2024-01-15"), date("2024-01-15")), 0);
+        assert_eq!(days_30_360(date("2024-01-15"), date("2025-01-15")), 360);
+    }
 }
@@ -0,0 +1,116 @@
+// src/telemetry.rs
+//! Logging and tracing. Logs are written to stdout as JSON lines, one per event and one as
+//! each span closes. When an OTLP collector is configured, spans are also exported to it,
+//! continuing the trace of a W3C `traceparent` header sent by the client.
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::Error;
+use anyhow::{Context, Result};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::env;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+const SERVICE_NAME: &str = "loan-api";
+
+/// What is exported when `TRACE_EXPORT_FILTER` is not set. Unlike the default for the logs,
+/// it includes the `sqlx::query` events carrying each statement and its row counts, which
+/// become events of the `LoanService` span they ran in.
+const DEFAULT_EXPORT_FILTER: &str = "info,sqlx::query=debug";
+
+/// Keeps the span exporter alive; call [`Telemetry::shutdown`] to flush it before exiting.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Installs the global subscriber. Logs are filtered by `RUST_LOG` (default `info`).
+    /// Spans are exported over OTLP/HTTP only when `OTEL_EXPORTER_OTLP_ENDPOINT` or
+    /// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set, e.g. to `http://localhost:4318` for a
+    /// local collector; `TRACE_EXPORT_FILTER` selects them and `OTEL_SERVICE_NAME` overrides
+    /// the service name.
+    pub fn init() -> Result<Self> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let log_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let logs = fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(log_filter);
+
+        let exporting = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+            .iter()
+            .any(|name| env::var_os(name).is_some());
+        let provider = if exporting { Some(tracer_provider()?) } else { None };
+        let traces = match &provider {
+            Some(provider) => {
+                let filter = env::var("TRACE_EXPORT_FILTER")
+                    .unwrap_or_else(|_| DEFAULT_EXPORT_FILTER.to_string());
+                let layer = tracing_opentelemetry::layer()
+                    .with_tracer(provider.tracer(SERVICE_NAME))
+                    .with_filter(EnvFilter::new(filter));
+                Some(layer)
+            }
+            None => None,
+        };
+
+        tracing_subscriber::registry()
+            .with(logs)
+            .with(traces)
+            .try_init()
+            .context("Failed to install the tracing subscriber")?;
+        Ok(Telemetry { provider })
+    }
+
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(error) = provider.shutdown() {
+                tracing::warn!("Failed to flush spans: {}", error);
+            }
+        }
+    }
+}
+
+fn tracer_provider() -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .build()
+        .context("Failed to create the OTLP span exporter")?;
+
+    let mut resource = Resource::builder();
+    if env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name(SERVICE_NAME);
+    }
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+/// The default request span, plus the correlation ID that [`crate::errors::CorrelationId`]
+/// records once it has settled on one.
+pub struct RequestSpan;
+
+impl RootSpanBuilder for RequestSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        tracing_actix_web::root_span!(request, correlation_id = tracing::field::Empty)
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}